@@ -1,11 +1,140 @@
 use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::collections::{HashMap, HashSet};
 
 pub struct Houses {
     s: String,
 }
 
+// Decides which deliverer makes each move.
+#[allow(dead_code)]
+pub enum Schedule {
+    // Deliverers take one move each, in turn.
+    RoundRobin,
+    // Each deliverer makes this many moves before handing over.
+    Blocks(usize),
+    // Deliverer n makes weights[n] moves before handing over.
+    Weighted(Vec<usize>),
+}
+
+impl Schedule {
+    // Whether this schedule can share moves among the deliverers.
+    fn check(&self, deliverers: usize) -> Result<(), String> {
+        if deliverers == 0 {
+            return Err("Need at least one deliverer.".to_string());
+        }
+        match self {
+            Schedule::RoundRobin => return Ok(()),
+            Schedule::Blocks(0) => return Err("Blocks need at least one move.".to_string()),
+            Schedule::Blocks(_) => return Ok(()),
+            Schedule::Weighted(weights) if weights.len() != deliverers =>
+                return Err(format!("Need one weight per deliverer, got {} for {}.", weights.len(), deliverers)),
+            Schedule::Weighted(weights) if weights.iter().all(|w| *w == 0) =>
+                return Err("Some deliverer needs a weight above zero.".to_string()),
+            Schedule::Weighted(_) => return Ok(()),
+        }
+    }
+
+    // Which of the deliverers makes move number move_no (counting from 0).
+    // The schedule must have passed check().
+    fn deliverer(&self, move_no: usize, deliverers: usize) -> usize {
+        match self {
+            Schedule::RoundRobin => move_no % deliverers,
+            Schedule::Blocks(k) => (move_no / k) % deliverers,
+            Schedule::Weighted(weights) => {
+                let mut n = move_no % weights.iter().sum::<usize>();
+                let mut who = 0;
+                while n >= weights[who] {
+                    n -= weights[who];
+                    who += 1;
+                }
+                who
+            }
+        }
+    }
+}
+
+// Outcome of a delivery: houses receiving at least one present, and how
+// many distinct houses each deliverer visited (including the start).
+#[derive(Debug, PartialEq)]
+pub struct Delivery {
+    pub houses: usize,
+    pub coverage: Vec<usize>,
+}
+
+// Number of presents delivered to each house.
+pub struct GiftMap {
+    counts: HashMap<(i32, i32), u32>,
+}
+
+#[allow(dead_code)]
+impl GiftMap {
+    pub fn gifts(&self, house: (i32, i32)) -> u32 {
+        return *self.counts.get(&house).unwrap_or(&0);
+    }
+
+    // Houses receiving at least one present.
+    pub fn houses(&self) -> usize {
+        return self.counts.len();
+    }
+
+    // House with the most presents and its count.  Ties go to the lowest
+    // (x, y) so the answer doesn't depend on hash order.
+    pub fn most_visited(&self) -> Option<((i32, i32), u32)> {
+        return self.counts.iter()
+            .map(|(house, n)| (*house, *n))
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)));
+    }
+
+    pub fn houses_with_at_least(&self, k: u32) -> usize {
+        return self.counts.values().filter(|n| **n >= k).count();
+    }
+
+    // Lowest and highest (x, y) of any house receiving presents.
+    pub fn bounding_box(&self) -> ((i32, i32), (i32, i32)) {
+        let mut min = (0, 0);
+        let mut max = (0, 0);
+        for (x, y) in self.counts.keys() {
+            min = (min.0.min(*x), min.1.min(*y));
+            max = (max.0.max(*x), max.1.max(*y));
+        }
+
+        return (min, max);
+    }
+
+    // One line per house, sorted by x then y, after a header line.
+    pub fn write_csv<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let mut houses: Vec<(&(i32, i32), &u32)> = self.counts.iter().collect();
+        houses.sort();
+
+        writeln!(out, "x,y,gifts")?;
+        for ((x, y), n) in houses {
+            writeln!(out, "{},{},{}", x, y, n)?;
+        }
+
+        return Ok(());
+    }
+
+    // Plain (P2) PGM image of the bounding box, north at the top, with each
+    // pixel's grey level being that house's present count.
+    pub fn write_pgm<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let (min, max) = self.bounding_box();
+        let max_gifts = self.most_visited().map(|(_, n)| n).unwrap_or(0).clamp(1, 65535);
+
+        writeln!(out, "P2")?;
+        writeln!(out, "{} {}", max.0 - min.0 + 1, max.1 - min.1 + 1)?;
+        writeln!(out, "{}", max_gifts)?;
+        for y in (min.1..=max.1).rev() {
+            let row: Vec<String> = (min.0..=max.0)
+                .map(|x| self.gifts((x, y)).min(max_gifts).to_string())
+                .collect();
+            writeln!(out, "{}", row.join(" "))?;
+        }
+
+        return Ok(());
+    }
+}
+
 impl Houses {
     pub fn load(filename: &str) -> Houses {
         let file = File::open(filename).unwrap();
@@ -22,59 +151,149 @@ impl Houses {
         return Houses { s: s.to_string() };
     }
 
-    pub fn visited(&self) -> usize {
-        let mut visited: HashMap<(i32, i32), u32> = HashMap::new();
-        let mut pos = (0, 0);
-        visited.insert(pos, 1);
-
-        for c in self.s.chars() {
-            match c {
-                '<' => pos.0 -= 1,
-                '>' => pos.0 += 1,
-                '^' => pos.1 += 1,
-                'v' => pos.1 -= 1,
-                _ => (),
-            }
+    fn step(pos: &mut (i32, i32), c: char) {
+        match c {
+            '<' => pos.0 -= 1,
+            '>' => pos.0 += 1,
+            '^' => pos.1 += 1,
+            'v' => pos.1 -= 1,
+            _ => (),
+        }
+    }
+
+    // Call deliver(who, house) for every present delivered: one per
+    // deliverer at the start, then one after each move.  Characters other
+    // than moves (such as a trailing newline) are skipped.
+    fn walk<F: FnMut(usize, (i32, i32))>(&self, deliverers: usize, schedule: &Schedule,
+                                         mut deliver: F) -> Result<(), String> {
+        schedule.check(deliverers)?;
+
+        let mut positions = vec![(0, 0); deliverers];
+        for who in 0..deliverers {
+            deliver(who, positions[who]);
+        }
 
-            *visited.entry(pos).or_insert(1) += 1;
+        let moves = self.s.chars().filter(|c| "<>^v".contains(*c));
+        for (move_no, c) in moves.enumerate() {
+            let who = schedule.deliverer(move_no, deliverers);
+            Houses::step(&mut positions[who], c);
+            deliver(who, positions[who]);
         }
 
-        return visited.len();
+        return Ok(());
     }
 
-    pub fn visited2(&self) -> usize {
-        let mut visited: HashMap<(i32, i32), u32> = HashMap::new();
-        let mut pos1 = (0, 0);
-        let mut pos2 = (0, 0);
-        visited.insert(pos1, 1);
-
-        let mut turn = 1;
-        for c in self.s.chars() {
-            if turn == 1 {
-                match c {
-                    '<' => pos1.0 -= 1,
-                    '>' => pos1.0 += 1,
-                    '^' => pos1.1 += 1,
-                    'v' => pos1.1 -= 1,
-                    _ => (),
+    // Deliver with any number of deliverers, all starting at (0, 0), taking
+    // moves from the instructions in the order given by schedule.
+    pub fn deliver(&self, deliverers: usize, schedule: &Schedule) -> Result<Delivery, String> {
+        let mut visited: HashSet<(i32, i32)> = HashSet::new();
+        let mut covered: Vec<HashSet<(i32, i32)>> = vec![HashSet::new(); deliverers];
+
+        self.walk(deliverers, schedule, |who, house| {
+            visited.insert(house);
+            covered[who].insert(house);
+        })?;
+
+        return Ok(Delivery {
+            houses: visited.len(),
+            coverage: covered.iter().map(|houses| houses.len()).collect(),
+        });
+    }
+
+    // Presents delivered to each house, same rules as deliver().
+    #[allow(dead_code)]
+    pub fn gift_map(&self, deliverers: usize, schedule: &Schedule) -> Result<GiftMap, String> {
+        let mut counts: HashMap<(i32, i32), u32> = HashMap::new();
+
+        self.walk(deliverers, schedule, |_, house| {
+            *counts.entry(house).or_insert(0) += 1;
+        })?;
+
+        return Ok(GiftMap { counts: counts });
+    }
+
+    // Number of moves in the instructions.
+    #[allow(dead_code)]
+    pub fn route_length(&self) -> usize {
+        return self.s.chars().filter(|c| "<>^v".contains(*c)).count();
+    }
+
+    fn distance(a: (i32, i32), b: (i32, i32)) -> usize {
+        return ((a.0 - b.0).abs() + (a.1 - b.1).abs()) as usize;
+    }
+
+    // A short sequence of moves, in the same ^v<> format, that starts at
+    // (0, 0) and reaches every house Santa visits on his own.  Finding the
+    // shortest is a travelling salesman problem, so this builds a
+    // nearest-neighbour tour and improves it with 2-opt; the result is never
+    // longer than the original route but may not be optimal.
+    #[allow(dead_code)]
+    pub fn optimized_route(&self) -> String {
+        // One deliverer, round robin: always a valid schedule.
+        let gifts = self.gift_map(1, &Schedule::RoundRobin).unwrap();
+        let mut remaining: Vec<(i32, i32)> = gifts.counts.keys()
+            .filter(|house| **house != (0, 0))
+            .cloned()
+            .collect();
+        remaining.sort();
+
+        // Nearest neighbour tour from the start
+        let mut tour = vec![(0, 0)];
+        while !remaining.is_empty() {
+            let here = tour[tour.len()-1];
+            let mut nearest = 0;
+            for n in 1..remaining.len() {
+                if Houses::distance(here, remaining[n]) < Houses::distance(here, remaining[nearest]) {
+                    nearest = n;
                 }
-                *visited.entry(pos1).or_insert(1) += 1;
-                turn = 2;
             }
-            else {
-                match c {
-                    '<' => pos2.0 -= 1,
-                    '>' => pos2.0 += 1,
-                    '^' => pos2.1 += 1,
-                    'v' => pos2.1 -= 1,
-                    _ => (),
+            tour.push(remaining.swap_remove(nearest));
+        }
+
+        // 2-opt: reverse tour[i..=j] while that shortens the route.  The
+        // start stays fixed and the end of the route is free.
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for i in 1..tour.len() {
+                for j in i+1..tour.len() {
+                    let mut before = Houses::distance(tour[i-1], tour[i]);
+                    let mut after = Houses::distance(tour[i-1], tour[j]);
+                    if j+1 < tour.len() {
+                        before += Houses::distance(tour[j], tour[j+1]);
+                        after += Houses::distance(tour[i], tour[j+1]);
+                    }
+                    if after < before {
+                        tour[i..=j].reverse();
+                        improved = true;
+                    }
                 }
-                *visited.entry(pos2).or_insert(1) += 1;
-                turn = 1;
             }
         }
 
-        return visited.len();
+        let mut route = String::new();
+        for leg in tour.windows(2) {
+            let (dx, dy) = (leg[1].0 - leg[0].0, leg[1].1 - leg[0].1);
+            let x_move = if dx < 0 { "<" } else { ">" };
+            let y_move = if dy < 0 { "v" } else { "^" };
+            route.push_str(&x_move.repeat(dx.unsigned_abs() as usize));
+            route.push_str(&y_move.repeat(dy.unsigned_abs() as usize));
+        }
+
+        // The nearest neighbour tour can, rarely, lose to the elves' route.
+        if route.len() > self.route_length() {
+            return self.s.chars().filter(|c| "<>^v".contains(*c)).collect();
+        }
+
+        return route;
+    }
+
+    pub fn visited(&self) -> usize {
+        return self.deliver(1, &Schedule::RoundRobin).unwrap().houses;
+    }
+
+    pub fn visited2(&self) -> usize {
+        return self.deliver(2, &Schedule::RoundRobin).unwrap().houses;
     }
 }
 
@@ -121,6 +340,104 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_deliver() {
+        let houses = Houses::from_str("^>v<");
+
+        assert_eq!(houses.deliver(1, &Schedule::RoundRobin),
+                   Ok(Delivery { houses: 4, coverage: vec![4] }));
+        assert_eq!(houses.deliver(2, &Schedule::RoundRobin),
+                   Ok(Delivery { houses: 3, coverage: vec![2, 2] }));
+        assert_eq!(houses.deliver(4, &Schedule::RoundRobin),
+                   Ok(Delivery { houses: 5, coverage: vec![2, 2, 2, 2] }));
+
+        // First santa goes ^>, the second v<
+        assert_eq!(houses.deliver(2, &Schedule::Blocks(2)),
+                   Ok(Delivery { houses: 5, coverage: vec![3, 3] }));
+
+        // First santa goes ^>v, the second <
+        assert_eq!(houses.deliver(2, &Schedule::Weighted(vec![3, 1])),
+                   Ok(Delivery { houses: 5, coverage: vec![4, 2] }));
+    }
+
+    #[test]
+    fn test_bad_schedules() {
+        let houses = Houses::from_str("^>v<");
+
+        assert_eq!(houses.deliver(0, &Schedule::RoundRobin), Err("Need at least one deliverer.".to_string()));
+        assert_eq!(houses.deliver(2, &Schedule::Blocks(0)), Err("Blocks need at least one move.".to_string()));
+        assert_eq!(houses.deliver(2, &Schedule::Weighted(vec![0, 0])),
+                   Err("Some deliverer needs a weight above zero.".to_string()));
+        assert_eq!(houses.deliver(3, &Schedule::Weighted(vec![1, 2])),
+                   Err("Need one weight per deliverer, got 2 for 3.".to_string()));
+        assert!(houses.gift_map(0, &Schedule::Blocks(1)).is_err());
+
+        // A zero weight just skips that deliverer.
+        assert_eq!(houses.deliver(2, &Schedule::Weighted(vec![0, 1])),
+                   Ok(Delivery { houses: 4, coverage: vec![1, 4] }));
+    }
+
+    #[test]
+    fn test_gift_map() {
+        // "delivers presents to 2 houses: one at the starting location, and
+        // one to the east"
+        let gifts = Houses::from_str(">").gift_map(1, &Schedule::RoundRobin).unwrap();
+        assert_eq!(gifts.gifts((0, 0)), 1);
+        assert_eq!(gifts.gifts((1, 0)), 1);
+
+        // "delivers a bunch of presents to some very lucky children at only
+        // 2 houses"
+        let gifts = Houses::from_str("^v^v^v^v^v\n").gift_map(1, &Schedule::RoundRobin).unwrap();
+        assert_eq!(gifts.houses(), 2);
+        assert_eq!(gifts.gifts((0, 0)), 6);
+        assert_eq!(gifts.gifts((0, 1)), 5);
+        assert_eq!(gifts.most_visited(), Some(((0, 0), 6)));
+        assert_eq!(gifts.houses_with_at_least(6), 1);
+
+        // Both santas deliver at the start
+        let gifts = Houses::from_str("^v").gift_map(2, &Schedule::RoundRobin).unwrap();
+        assert_eq!(gifts.gifts((0, 0)), 2);
+        assert_eq!(gifts.most_visited(), Some(((0, 0), 2)));
+    }
+
+    #[test]
+    fn test_heatmap() {
+        let gifts = Houses::from_str("^>>vv").gift_map(1, &Schedule::RoundRobin).unwrap();
+        assert_eq!(gifts.bounding_box(), ((0, -1), (2, 1)));
+
+        let mut out: Vec<u8> = Vec::new();
+        gifts.write_pgm(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "P2\n3 3\n1\n1 1 1\n1 0 1\n0 0 1\n");
+
+        let mut out: Vec<u8> = Vec::new();
+        gifts.write_csv(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "x,y,gifts\n0,0,1\n0,1,1\n1,1,1\n2,-1,1\n2,0,1\n2,1,1\n");
+    }
+
+    #[test]
+    fn test_optimized_route() {
+        let examples: Vec<(&str, usize)> = vec![
+            ("^>v<", 3),
+            ("^v^v^v^v^v", 1),
+            (">>>><<<<<<<<", 12),
+            ("^^>>vv<<^>", 8),
+        ];
+
+        for (s, shortest) in examples {
+            let houses = Houses::from_str(s);
+            let route = houses.optimized_route();
+            assert_eq!(route.len(), shortest);
+
+            // The new route reaches every house the old one did.
+            let old = houses.gift_map(1, &Schedule::RoundRobin).unwrap();
+            let new = Houses::from_str(&route).gift_map(1, &Schedule::RoundRobin).unwrap();
+            assert_eq!(new.houses(), old.houses());
+            for house in old.counts.keys() {
+                assert!(new.gifts(*house) > 0);
+            }
+        }
+    }
+
     #[test]
     fn test_part1() {
         let mut day = Houses::load("data/day3_input.txt");
@@ -1,169 +1,773 @@
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
 use lazy_static::lazy_static;
 use regex::Regex;
 
-// Operations we read from the input file
-enum Operation { ON, OFF, TOGGLE, }
+// Operations we read from the input file: "turn on", "turn on R,G,B",
+// "turn off", "toggle", "set N", "dim N" and "invert".
+#[derive(Debug, PartialEq)]
+pub enum Operation { ON, COLOUR(u8, u8, u8), OFF, TOGGLE, SET(u64), DIM(u64), INVERT, }
+
+// How a light responds to each operation and what it adds to the total.
+// Lights::run() and run_compressed() do the rectangle iteration for any
+// model.
+pub trait LightModel {
+    type Cell: Clone;
+    // What an operation does to a cell, as a map that composes with others.
+    // Models whose operations don't compose use () and never return one.
+    type Map: CellMap<Self::Cell>;
+
+    // State of every light before the first instruction.
+    fn initial(&self) -> Self::Cell;
+    fn apply(&self, cell: &mut Self::Cell, op: &Operation);
+    // One light's contribution to the total.
+    fn value(&self, cell: &Self::Cell) -> u64;
+
+    // The operation as a map, or None if it can't be composed; then
+    // run_compressed() has to apply operations one at a time.
+    fn map(&self, _op: &Operation) -> Option<Self::Map> { None }
+}
+
+// A function on cells that can be composed with others of its kind without
+// knowing the cell it will be applied to.
+pub trait CellMap<C>: Copy + PartialEq {
+    fn identity() -> Self;
+    // Self followed by next.
+    fn then(&self, next: &Self) -> Self;
+    fn apply(&self, cell: &C) -> C;
+}
+
+impl<C: Clone> CellMap<C> for () {
+    fn identity() {}
+    fn then(&self, _next: &()) {}
+    fn apply(&self, cell: &C) -> C { cell.clone() }
+}
+
+// x -> (x & and) ^ xor, which is on, off, toggle or leave alone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoolMap {
+    and: bool,
+    xor: bool,
+}
+
+impl CellMap<bool> for BoolMap {
+    fn identity() -> BoolMap { BoolMap { and: true, xor: false } }
+
+    fn then(&self, next: &BoolMap) -> BoolMap {
+        return BoolMap { and: self.and && next.and, xor: (self.xor && next.and) ^ next.xor };
+    }
+
+    fn apply(&self, lit: &bool) -> bool { (*lit && self.and) ^ self.xor }
+}
+
+// Far enough from any level that adding up shifts can't overflow.
+const UNBOUNDED: i64 = i64::MAX / 4;
+
+// x -> min(hi, max(lo, x + shift)), which covers every brightness operation
+// but invert.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Clamp {
+    lo: i64,
+    hi: i64,
+    shift: i64,
+}
+
+impl Clamp {
+    // Add shift, then keep the level between 0 and hi.
+    fn shift(shift: i64, hi: i64) -> Clamp {
+        return Clamp { lo: 0, hi, shift: shift.clamp(-UNBOUNDED, UNBOUNDED) };
+    }
+
+    fn set(level: i64) -> Clamp {
+        return Clamp { lo: level, hi: level, shift: 0 };
+    }
+}
+
+impl CellMap<u64> for Clamp {
+    fn identity() -> Clamp { Clamp { lo: -UNBOUNDED, hi: UNBOUNDED, shift: 0 } }
+
+    fn then(&self, next: &Clamp) -> Clamp {
+        // Shifting after a clamp is clamping to shifted bounds after the
+        // shift, and clamping twice clamps to the first bounds clamped by
+        // the second.
+        let bound = |b: i64| (b + next.shift).max(next.lo).min(next.hi);
+        return Clamp { lo: bound(self.lo), hi: bound(self.hi),
+                       shift: (self.shift + next.shift).clamp(-UNBOUNDED, UNBOUNDED) };
+    }
+
+    fn apply(&self, level: &u64) -> u64 {
+        return (*level as i64 + self.shift).clamp(self.lo, self.hi) as u64;
+    }
+}
+
+// Part 1: lights are on or off; the total is how many are on.  Turning on
+// in any colour turns a light on.
+pub struct OnOff;
+
+impl LightModel for OnOff {
+    type Cell = bool;
+    type Map = BoolMap;
+
+    fn initial(&self) -> bool { false }
+
+    fn apply(&self, lit: &mut bool, op: &Operation) {
+        match op {
+            Operation::ON | Operation::COLOUR(..) => *lit = true,
+            Operation::OFF => *lit = false,
+            Operation::TOGGLE | Operation::INVERT => *lit ^= true,
+            // Set to any level above zero is on; any dimming turns it off.
+            Operation::SET(n) => *lit = *n > 0,
+            Operation::DIM(n) => *lit &= *n == 0,
+        }
+    }
+
+    fn value(&self, lit: &bool) -> u64 { *lit as u64 }
+
+    fn map(&self, op: &Operation) -> Option<BoolMap> {
+        let (and, xor) = match op {
+            Operation::ON | Operation::COLOUR(..) => (false, true),
+            Operation::OFF => (false, false),
+            Operation::TOGGLE | Operation::INVERT => (true, true),
+            Operation::SET(n) => (false, *n > 0),
+            Operation::DIM(n) => (*n == 0, false),
+        };
+
+        return Some(BoolMap { and, xor });
+    }
+}
+
+// Part 2: on adds 1 to a light's brightness, off takes away 1 down to 0 and
+// toggle adds 2; the total is the overall brightness.  Turning on in a colour
+// counts as on.  Set and dim change the brightness to or by their level;
+// invert lights a dark light to 1 and puts out any other.
+pub struct Brightness;
+
+impl LightModel for Brightness {
+    type Cell = u64;
+    type Map = Clamp;
+
+    fn initial(&self) -> u64 { 0 }
+
+    fn apply(&self, brightness: &mut u64, op: &Operation) {
+        match op {
+            Operation::ON | Operation::COLOUR(..) => *brightness += 1,
+            Operation::OFF => *brightness = brightness.saturating_sub(1),
+            Operation::TOGGLE => *brightness += 2,
+            Operation::SET(n) => *brightness = *n,
+            Operation::DIM(n) => *brightness = brightness.saturating_sub(*n),
+            Operation::INVERT => *brightness = (*brightness == 0) as u64,
+        }
+    }
+
+    fn value(&self, brightness: &u64) -> u64 { *brightness }
+
+    fn map(&self, op: &Operation) -> Option<Clamp> {
+        return level_map(op, UNBOUNDED as u64);
+    }
+}
+
+// Brightness operations other than invert as a Clamp that also keeps the
+// level at or below max.
+fn level_map(op: &Operation, max: u64) -> Option<Clamp> {
+    if max > UNBOUNDED as u64 {
+        return None;
+    }
+    let max = max as i64;
+    let level = |n: &u64| (*n).min(UNBOUNDED as u64) as i64;
+
+    let clamp = match op {
+        Operation::ON | Operation::COLOUR(..) => Clamp::shift(1, max),
+        Operation::OFF => Clamp::shift(-1, max),
+        Operation::TOGGLE => Clamp::shift(2, max),
+        Operation::SET(n) if level(n) < UNBOUNDED => Clamp::set(level(n).min(max)),
+        Operation::DIM(n) => Clamp::shift(-level(n), max),
+        // Invert isn't monotonic, and a level too high to track can't be
+        // set.
+        _ => return None,
+    };
+
+    return Some(clamp);
+}
+
+// Like Brightness, but no light goes above max, and invert reflects the
+// level, so max becomes 0 and 0 becomes max.
+#[allow(dead_code)]
+pub struct CappedDimmer {
+    pub max: u64,
+}
+
+impl LightModel for CappedDimmer {
+    type Cell = u64;
+    type Map = Clamp;
+
+    fn initial(&self) -> u64 { 0 }
+
+    fn apply(&self, level: &mut u64, op: &Operation) {
+        match op {
+            Operation::INVERT => *level = self.max - *level,
+            _ => {
+                Brightness.apply(level, op);
+                *level = (*level).min(self.max);
+            }
+        }
+    }
+
+    fn value(&self, level: &u64) -> u64 { *level }
+
+    fn map(&self, op: &Operation) -> Option<Clamp> {
+        return level_map(op, self.max);
+    }
+}
+
+// Colour lights: "turn on R,G,B" mixes that colour into a light additively,
+// so red then blue makes magenta, and a plain "turn on" mixes in the model's
+// colour.  Off turns a light black and toggle or invert inverts every
+// channel.  Set makes a light grey at the given level and dim darkens every
+// channel by it.  The total is the sum of all channels of all lights.
+#[allow(dead_code)]
+pub struct Rgb {
+    pub colour: (u8, u8, u8),
+}
+
+impl LightModel for Rgb {
+    type Cell = (u8, u8, u8);
+    type Map = ();
+
+    fn initial(&self) -> (u8, u8, u8) { (0, 0, 0) }
+
+    fn apply(&self, rgb: &mut (u8, u8, u8), op: &Operation) {
+        match op {
+            Operation::ON => {
+                *rgb = (rgb.0.saturating_add(self.colour.0),
+                        rgb.1.saturating_add(self.colour.1),
+                        rgb.2.saturating_add(self.colour.2));
+            }
+            Operation::COLOUR(r, g, b) => {
+                *rgb = (rgb.0.saturating_add(*r), rgb.1.saturating_add(*g), rgb.2.saturating_add(*b));
+            }
+            Operation::OFF => *rgb = (0, 0, 0),
+            Operation::TOGGLE | Operation::INVERT => *rgb = (255 - rgb.0, 255 - rgb.1, 255 - rgb.2),
+            Operation::SET(n) => {
+                let grey = (*n).min(255) as u8;
+                *rgb = (grey, grey, grey);
+            }
+            Operation::DIM(n) => {
+                let by = (*n).min(255) as u8;
+                *rgb = (rgb.0.saturating_sub(by), rgb.1.saturating_sub(by), rgb.2.saturating_sub(by));
+            }
+        }
+    }
+
+    fn value(&self, rgb: &(u8, u8, u8)) -> u64 {
+        rgb.0 as u64 + rgb.1 as u64 + rgb.2 as u64
+    }
+}
 
 // Represents one line of the input file.
 struct Instruction {
     op: Operation,
-    x1: usize,
-    y1: usize,
-    x2: usize,
-    y2: usize,
+    x1: i64,
+    y1: i64,
+    x2: i64,
+    y2: i64,
+    // Line number in the input, for error messages
+    line: usize,
+}
+
+// The rectangle of lights: x runs from x_min to x_min+width-1 and y from
+// y_min to y_min+height-1.  A negative x_min or y_min makes room for
+// negative coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Grid {
+    pub x_min: i64,
+    pub y_min: i64,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Grid {
+    // A grid with its corner at 0,0.
+    pub fn new(width: usize, height: usize) -> Grid {
+        Grid { x_min: 0, y_min: 0, width: width, height: height }
+    }
+
+    fn contains(&self, x: i64, y: i64) -> bool {
+        return x >= self.x_min && x < self.x_min + self.width as i64 &&
+            y >= self.y_min && y < self.y_min + self.height as i64;
+    }
+
+    // Position of light x,y in the state vectors.
+    fn index(&self, x: i64, y: i64) -> (usize, usize) {
+        return ((x - self.x_min) as usize, (y - self.y_min) as usize);
+    }
 }
 
 // Represents the problem, holds a vector of instructions.
 pub struct Lights {
     instructions: Vec<Instruction>,
+    grid: Grid,
 }
 
 impl Lights {
     // Read the input file, store the instructions found there and return Lights object.
     pub fn load(filename: &str) -> Lights {
+        return Lights::try_load(filename).unwrap();
+    }
+
+    pub fn try_load(filename: &str) -> Result<Lights, String> {
+        let file = File::open(filename).map_err(|e| format!("{}: {}", filename, e))?;
+        let reader = BufReader::new(file);
         let mut instructions: Vec<Instruction> = Vec::new();
 
-        lazy_static! {
-            static ref INSTR_RE: Regex = Regex::new("(.*) ([0-9]+),([0-9]+) through ([0-9]+),([0-9]+)").unwrap();
+        for (n, line) in reader.lines().enumerate() {
+            let l = line.map_err(|e| format!("{}: {}", filename, e))?;
+            if !l.trim().is_empty() {
+                instructions.push(Lights::parse_instruction(&l, n+1)?);
+            }
         }
-        let file = File::open(filename).unwrap();
-        let reader = BufReader::new(file);
 
-        for line in reader.lines() {
-            let l = &line.unwrap();
-            match INSTR_RE.captures(l) {
-                Some(cap) => {
-                    let x1: usize = cap[2].parse().unwrap();
-                    let y1: usize = cap[3].parse().unwrap();
-                    let x2: usize = cap[4].parse().unwrap();
-                    let y2: usize = cap[5].parse().unwrap();
-
-                    let op = match &cap[1] {
-                        "turn on" => Operation::ON,
-                        "turn off" => Operation::OFF,
-                        "toggle" => Operation::TOGGLE,
-                        _ => Operation::OFF,
-                    };
-                    instructions.push(Instruction {op: op, x1: x1, y1: y1, x2: x2, y2: y2})
-                }
-                _ => {}
+        Ok(Lights { instructions: instructions, grid: Grid::new(1000, 1000) })
+    }
+
+    // Instructions one per line, as in the input file.
+    #[allow(dead_code)]
+    pub fn from_str(s: &str) -> Result<Lights, String> {
+        let mut instructions: Vec<Instruction> = Vec::new();
+        for (n, l) in s.lines().enumerate() {
+            if !l.trim().is_empty() {
+                instructions.push(Lights::parse_instruction(l, n+1)?);
             }
         }
 
-        Lights { instructions: instructions }
+        Ok(Lights { instructions: instructions, grid: Grid::new(1000, 1000) })
     }
 
-    // Interpret the instructions per part 1 and return the number of lights lit.
-    fn run_part1(&self) -> u32 {
-        let mut state = vec![vec![false; 1000]; 1000];
+    // Parse "<operation> x1,y1 through x2,y2", where x1,y1 is the corner
+    // with the lower coordinates.
+    fn parse_instruction(l: &str, line: usize) -> Result<Instruction, String> {
+        lazy_static! {
+            static ref INSTR_RE: Regex = Regex::new("^(.*?) *(-?[0-9]+),(-?[0-9]+) through (-?[0-9]+),(-?[0-9]+)$").unwrap();
+        }
 
-        // Run the instructions per part1
+        let cap = match INSTR_RE.captures(l.trim()) {
+            Some(cap) => cap,
+            None => return Err(format!("Line {}: expected '<operation> x1,y1 through x2,y2', found '{}'.",
+                                       line, l.trim())),
+        };
+
+        let coordinate = |n: usize| -> Result<i64, String> {
+            cap[n].parse().map_err(|_| format!("Line {}: coordinate {} is out of range.", line, &cap[n]))
+        };
+        let x1 = coordinate(2)?;
+        let y1 = coordinate(3)?;
+        let x2 = coordinate(4)?;
+        let y2 = coordinate(5)?;
+        if x1 > x2 || y1 > y2 {
+            return Err(format!("Line {}: corners {},{} through {},{} are swapped; the first should be the lower.",
+                               line, x1, y1, x2, y2));
+        }
+
+        let words: Vec<&str> = cap[1].split_whitespace().collect();
+        let level = |word: &str| -> Result<u64, String> {
+            word.parse().map_err(|_| format!("Line {}: '{}' is not a level for {}.", line, word, words[0]))
+        };
+        let colour = |word: &str| -> Result<Operation, String> {
+            let channels: Vec<Result<u8, _>> = word.split(',').map(|c| c.parse::<u8>()).collect();
+            match channels[..] {
+                [Ok(r), Ok(g), Ok(b)] => Ok(Operation::COLOUR(r, g, b)),
+                _ => Err(format!("Line {}: '{}' is not a colour; expected R,G,B from 0 to 255.", line, word)),
+            }
+        };
+        let op = match words[..] {
+            ["turn", "on"] => Operation::ON,
+            ["turn", "on", rgb] => colour(rgb)?,
+            ["turn", "off"] => Operation::OFF,
+            ["toggle"] => Operation::TOGGLE,
+            ["invert"] => Operation::INVERT,
+            ["set", n] => Operation::SET(level(n)?),
+            ["dim", n] => Operation::DIM(level(n)?),
+            _ => return Err(format!("Line {}: unknown operation '{}'.", line, &cap[1])),
+        };
+
+        Ok(Instruction {op: op, x1: x1, y1: y1, x2: x2, y2: y2, line: line})
+    }
+
+    #[allow(dead_code)]
+    pub fn set_grid(&mut self, grid: Grid) {
+        self.grid = grid;
+    }
+
+    // Size the grid to just cover every instruction.
+    #[allow(dead_code)]
+    pub fn infer_grid(&mut self) {
+        if self.instructions.is_empty() {
+            self.grid = Grid::new(0, 0);
+            return;
+        }
+
+        let x_min = self.instructions.iter().map(|i| i.x1.min(i.x2)).min().unwrap();
+        let y_min = self.instructions.iter().map(|i| i.y1.min(i.y2)).min().unwrap();
+        let x_max = self.instructions.iter().map(|i| i.x1.max(i.x2)).max().unwrap();
+        let y_max = self.instructions.iter().map(|i| i.y1.max(i.y2)).max().unwrap();
+        self.grid = Grid {
+            x_min: x_min,
+            y_min: y_min,
+            width: (x_max - x_min + 1) as usize,
+            height: (y_max - y_min + 1) as usize,
+        };
+    }
+
+    // Make sure every instruction stays on the grid.
+    pub fn check_bounds(&self) -> Result<(), String> {
         for i in &self.instructions {
-            match i.op {
-                Operation::ON => {
-                    // Turn on some lights
-                    for x in i.x1..=i.x2 {
-                        for y in i.y1..=i.y2 {
-                            state[x][y] = true;
-                        }
-                    }
+            if !self.grid.contains(i.x1, i.y1) || !self.grid.contains(i.x2, i.y2) {
+                return Err(format!("Line {}: {},{} through {},{} is off the {}x{} grid starting at {},{}.",
+                                   i.line, i.x1, i.y1, i.x2, i.y2,
+                                   self.grid.width, self.grid.height, self.grid.x_min, self.grid.y_min));
+            }
+        }
+
+        return Ok(());
+    }
+
+    // Run the instructions on every light of the grid and return the final
+    // state of each, indexed [x][y] from the grid's corner.  step(n, state)
+    // is called after instruction n (counting from 0) has been applied.
+    fn run_steps<M, F>(&self, model: &M, mut step: F) -> Result<Vec<Vec<M::Cell>>, String>
+        where M: LightModel, F: FnMut(usize, &Vec<Vec<M::Cell>>) {
+        self.check_bounds()?;
+        let mut state = vec![vec![model.initial(); self.grid.height]; self.grid.width];
+
+        for (n, i) in self.instructions.iter().enumerate() {
+            let (x1, y1) = self.grid.index(i.x1, i.y1);
+            let (x2, y2) = self.grid.index(i.x2, i.y2);
+            for column in &mut state[x1..=x2] {
+                for cell in &mut column[y1..=y2] {
+                    model.apply(cell, &i.op);
                 }
-                Operation::OFF => {
-                    // Turn off some lights
-                    for x in i.x1..=i.x2 {
-                        for y in i.y1..=i.y2 {
-                            state[x][y] = false;
-                        }
-                    }
+            }
+            step(n, &state);
+        }
+
+        return Ok(state);
+    }
+
+    fn final_state<M: LightModel>(&self, model: &M) -> Result<Vec<Vec<M::Cell>>, String> {
+        return self.run_steps(model, |_, _| {});
+    }
+
+    // Binary PBM image, lit lights white, with the grid's first row at the
+    // top.
+    fn write_pbm<W: Write>(state: &[Vec<bool>], mut out: W) -> io::Result<()> {
+        let width = state.len();
+        let height = state.first().map_or(0, |column| column.len());
+
+        write!(out, "P4\n{} {}\n", width, height)?;
+        for y in 0..height {
+            // Eight lights per byte, 1 bits black, each row padded to a byte
+            let mut row = vec![0u8; width.div_ceil(8)];
+            for (x, column) in state.iter().enumerate() {
+                if !column[y] {
+                    row[x / 8] |= 0x80 >> (x % 8);
                 }
-                Operation::TOGGLE => {
-                    // Toggle some lights
-                    for x in i.x1..=i.x2 {
-                        for y in i.y1..=i.y2 {
-                            state[x][y] ^= true;
-                        }
-                    }
+            }
+            out.write_all(&row)?;
+        }
+
+        return Ok(());
+    }
+
+    // Binary PGM image with brightness scaled so the brightest light is
+    // white.
+    fn write_pgm<W: Write>(state: &[Vec<u64>], mut out: W) -> io::Result<()> {
+        let width = state.len();
+        let height = state.first().map_or(0, |column| column.len());
+        let max = state.iter().flatten().cloned().max().unwrap_or(0).max(1);
+
+        write!(out, "P5\n{} {}\n255\n", width, height)?;
+        for y in 0..height {
+            let row: Vec<u8> = state.iter().map(|column| (column[y] * 255 / max) as u8).collect();
+            out.write_all(&row)?;
+        }
+
+        return Ok(());
+    }
+
+    // Image of the final display under the part 1 rules.
+    #[allow(dead_code)]
+    pub fn render_pbm<W: Write>(&self, out: W) -> Result<(), String> {
+        let state = self.final_state(&OnOff)?;
+
+        return Lights::write_pbm(&state, out).map_err(|e| e.to_string());
+    }
+
+    // Image of the final display under the part 2 rules.
+    #[allow(dead_code)]
+    pub fn render_pgm<W: Write>(&self, out: W) -> Result<(), String> {
+        let state = self.final_state(&Brightness)?;
+
+        return Lights::write_pgm(&state, out).map_err(|e| e.to_string());
+    }
+
+    // Write an image after every instruction into dir, as frame_0000.pbm,
+    // frame_0001.pbm, ... or, with brightness, the part 2 display as .pgm
+    // files.  Each PGM frame is scaled to its own brightest light.  Returns
+    // the number of frames written.
+    #[allow(dead_code)]
+    pub fn dump_frames(&self, dir: &Path, brightness: bool) -> Result<usize, String> {
+        let mut error: Option<io::Error> = None;
+        let mut save = |n: usize, extension: &str, write: &dyn Fn(BufWriter<File>) -> io::Result<()>| {
+            if error.is_none() {
+                let path = dir.join(format!("frame_{:04}.{}", n, extension));
+                if let Err(e) = File::create(path).and_then(|file| write(BufWriter::new(file))) {
+                    error = Some(e);
                 }
             }
+        };
+
+        if brightness {
+            self.run_steps(&Brightness, |n, state| save(n, "pgm", &|out| Lights::write_pgm(state, out)))?;
         }
+        else {
+            self.run_steps(&OnOff, |n, state| save(n, "pbm", &|out| Lights::write_pbm(state, out)))?;
+        }
+
+        match error {
+            Some(e) => return Err(format!("Writing frames to {}: {}", dir.display(), e)),
+            None => return Ok(self.instructions.len()),
+        }
+    }
 
-        Lights::num_lit(state)
+    // Run the instructions under the given model and total up the lights.
+    pub fn run<M: LightModel>(&self, model: &M) -> Result<u64, String> {
+        let state = self.final_state(model)?;
+
+        return Ok(state.iter().flatten().map(|cell| model.value(cell)).sum());
     }
 
-    fn run_part2(&self) -> u32 {
-        let mut state = vec![vec![0; 1000]; 1000];
+    // Interpret the instructions per part 1 and return the number of lights lit.
+    fn run_part1(&self) -> Result<u64, String> {
+        return self.run(&OnOff);
+    }
+
+    fn run_part2(&self) -> Result<u64, String> {
+        return self.run(&Brightness);
+    }
 
-        // Run the instructions per part1
+    // Region boundaries along one axis: every coordinate where some
+    // instruction's rectangle starts or stops.
+    fn boundaries(&self, corners: fn(&Instruction) -> (i64, i64)) -> Vec<i64> {
+        let mut edges: Vec<i64> = Vec::new();
         for i in &self.instructions {
-            match i.op {
-                Operation::ON => {
-                    // Turn on some lights
-                    for x in i.x1..=i.x2 {
-                        for y in i.y1..=i.y2 {
-                            state[x][y] += 1;
-                        }
-                    }
-                }
-                Operation::OFF => {
-                    // Turn off some lights
-                    for x in i.x1..=i.x2 {
-                        for y in i.y1..=i.y2 {
-                            if state[x][y] > 0 {
-                                state[x][y] -= 1;
-                            }
-                        }
-                    }
-                }
-                Operation::TOGGLE => {
-                    // Toggle some lights
-                    for x in i.x1..=i.x2 {
-                        for y in i.y1..=i.y2 {
-                            state[x][y] += 2;
-                        }
+            let (lo, hi) = corners(i);
+            edges.push(lo);
+            edges.push(hi + 1);
+        }
+        edges.sort();
+        edges.dedup();
+
+        return edges;
+    }
+
+    // Same answer as run(), but on a coordinate-compressed grid where each
+    // cell stands for a whole rectangle of lights that every instruction
+    // treats alike.  Cost depends on the number of such regions, not on the
+    // size of the grid: k instructions make at most 2k x 2k regions.
+    // Operations such as toggle and set don't commute, so the models can't
+    // be summed with difference arrays.  When the model can compose its
+    // operations, a sweep over the x edges keeps the covering instructions'
+    // maps composed in a segment tree, each node as a map over the y
+    // regions, so each edge costs O(k) and the whole run O(k^2) time and
+    // O(k log k) memory.  Otherwise columns of regions are worked out one
+    // at a time, applying every instruction to every region it covers, which
+    // is O(k^3) time and O(k) memory.
+    #[allow(dead_code)]
+    pub fn run_compressed<M: LightModel>(&self, model: &M) -> Result<u64, String> {
+        self.check_bounds()?;
+        let xs = self.boundaries(|i| (i.x1, i.x2));
+        let ys = self.boundaries(|i| (i.y1, i.y2));
+        if xs.is_empty() {
+            return Ok(0);
+        }
+
+        // (x_lo, x_hi, y_lo, y_hi) in region indexes, half open.
+        let spans: Vec<(usize, usize, usize, usize)> = self.instructions.iter().map(|i| (
+            xs.binary_search(&i.x1).unwrap(),
+            xs.binary_search(&(i.x2 + 1)).unwrap(),
+            ys.binary_search(&i.y1).unwrap(),
+            ys.binary_search(&(i.y2 + 1)).unwrap(),
+        )).collect();
+
+        let maps: Option<Vec<M::Map>> = self.instructions.iter().map(|i| model.map(&i.op)).collect();
+        if let Some(maps) = maps {
+            return Ok(Lights::sweep(model, &maps, &xs, &ys, &spans));
+        }
+
+        let mut total = 0;
+        let mut column = vec![model.initial(); ys.len() - 1];
+        for x in 0..xs.len() - 1 {
+            column.fill(model.initial());
+            for (i, (x_lo, x_hi, y_lo, y_hi)) in self.instructions.iter().zip(&spans) {
+                if *x_lo <= x && x < *x_hi {
+                    for cell in &mut column[*y_lo..*y_hi] {
+                        model.apply(cell, &i.op);
                     }
                 }
             }
+
+            let width = (xs[x+1] - xs[x]) as u64;
+            for (y, cell) in column.iter().enumerate() {
+                let height = (ys[y+1] - ys[y]) as u64;
+                total += model.value(cell) * width * height;
+            }
         }
 
-        Lights::brightness(state)
+        return Ok(total);
+    }
+
+    // run_compressed() for models whose operations compose: maps[i] is
+    // what instruction i does to each region in spans[i].  Sweeping the
+    // columns of regions in x order, instructions are turned on in the tree
+    // at their first column and off after their last.
+    fn sweep<M: LightModel>(model: &M, maps: &[M::Map], xs: &[i64], ys: &[i64],
+                            spans: &[(usize, usize, usize, usize)]) -> u64 {
+        let mut starts = vec![Vec::new(); xs.len()];
+        let mut stops = vec![Vec::new(); xs.len()];
+        for (i, (x_lo, x_hi, _, _)) in spans.iter().enumerate() {
+            starts[*x_lo].push(i);
+            stops[*x_hi].push(i);
+        }
+
+        let regions = ys.len() - 1;
+        let mut tree: MapTree<M> = MapTree::new(maps.len(), regions);
+        let mut total = 0;
+        for x in 0..xs.len() - 1 {
+            for i in &stops[x] {
+                tree.set(*i, None);
+            }
+            for i in &starts[x] {
+                let (_, _, y_lo, y_hi) = spans[*i];
+                tree.set(*i, Some((y_lo, y_hi, &maps[*i])));
+            }
+
+            let width = (xs[x+1] - xs[x]) as u64;
+            let pieces = tree.root();
+            for (p, (start, map)) in pieces.iter().enumerate() {
+                let end = pieces.get(p + 1).map_or(regions, |next| next.0);
+                let height = (ys[end] - ys[*start]) as u64;
+                total += model.value(&map.apply(&model.initial())) * width * height;
+            }
+        }
+
+        return total;
+    }
+
+    // Same answer as run_part1(), for grids of any size.
+    #[cfg(test)]
+    fn run_part1_compressed(&self) -> Result<u64, String> {
+        return self.run_compressed(&OnOff);
+    }
+
+    // Same answer as run_part2(), for grids of any size.
+    #[cfg(test)]
+    fn run_part2_compressed(&self) -> Result<u64, String> {
+        return self.run_compressed(&Brightness);
     }
+}
 
-    fn num_lit(state: Vec<Vec<bool>>) -> u32 {
-        let mut count = 0;
+// Segment tree over the instructions for Lights::sweep().  Each node holds
+// the maps of the active instructions under it, composed in order, as a
+// piecewise map over the y regions: (first region, map) pieces that
+// between them cover every region.  A node has at most as many pieces as
+// its children together, so turning one instruction on or off costs O(k).
+struct MapTree<M: LightModel> {
+    leaves: usize,
+    regions: usize,
+    nodes: Vec<Vec<(usize, M::Map)>>,
+}
 
-        for x in 0..1000 {
-            for y in 0..1000 {
-                if state[x][y] {
-                    count += 1;
+impl<M: LightModel> MapTree<M> {
+    fn new(instructions: usize, regions: usize) -> MapTree<M> {
+        let leaves = instructions.next_power_of_two();
+        return MapTree { leaves, regions, nodes: vec![vec![(0, M::Map::identity())]; 2 * leaves] };
+    }
+
+    // Make instruction i apply map to regions lo..hi, or nothing if span
+    // is None, and recompose everything above it.
+    fn set(&mut self, i: usize, span: Option<(usize, usize, &M::Map)>) {
+        let mut node = self.leaves + i;
+        let leaf = &mut self.nodes[node];
+        leaf.clear();
+        match span {
+            Some((lo, hi, map)) => {
+                if lo > 0 {
+                    leaf.push((0, M::Map::identity()));
+                }
+                leaf.push((lo, *map));
+                if hi < self.regions {
+                    leaf.push((hi, M::Map::identity()));
                 }
             }
+            None => leaf.push((0, M::Map::identity())),
         }
 
-        count
+        while node > 1 {
+            node /= 2;
+            self.compose(node);
+        }
     }
 
-    fn brightness(state: Vec<Vec<u32>>) -> u32 {
-        let mut count = 0;
+    // Merge the children's pieces, the left child's instructions first.
+    fn compose(&mut self, node: usize) {
+        // Refill the node's own buffer, which is already about the right
+        // size.
+        let mut pieces = std::mem::take(&mut self.nodes[node]);
+        pieces.clear();
+        let first = &self.nodes[2 * node];
+        let second = &self.nodes[2 * node + 1];
+
+        let (mut i, mut j) = (0, 0);
+        loop {
+            // Neighbouring regions that end up alike share a piece.
+            let map = first[i].1.then(&second[j].1);
+            if pieces.last().map(|last| last.1) != Some(map) {
+                pieces.push((first[i].0.max(second[j].0), map));
+            }
 
-        for x in 0..1000 {
-            for y in 0..1000 {
-                count += state[x][y];
+            let next_first = first.get(i + 1).map_or(usize::MAX, |piece| piece.0);
+            let next_second = second.get(j + 1).map_or(usize::MAX, |piece| piece.0);
+            if next_first == usize::MAX && next_second == usize::MAX {
+                break;
             }
+            // Step past whichever boundary comes first, or both if they
+            // coincide.
+            i += (next_first <= next_second) as usize;
+            j += (next_second <= next_first) as usize;
         }
 
-        count
+        self.nodes[node] = pieces;
+    }
+
+    // Every active instruction composed, as (first region, map) pieces.
+    fn root(&self) -> &[(usize, M::Map)] {
+        return &self.nodes[1];
     }
 }
 
 impl super::Day for Lights {
     fn part1(&mut self) -> Result<i64, &str> {
-        return Ok(self.run_part1() as i64);
+        match self.run_part1() {
+            Ok(count) => return Ok(count as i64),
+            Err(_) => return Err("An instruction is off the grid."),
+        }
     }
 
     fn part2(&mut self) -> Result<i64, &str> {
-        return Ok(self.run_part2() as i64);
+        match self.run_part2() {
+            Ok(brightness) => return Ok(brightness as i64),
+            Err(_) => return Err("An instruction is off the grid."),
+        }
     }
 }
 
@@ -171,21 +775,309 @@ impl super::Day for Lights {
 mod tests {
     use super::*;
     use crate::Day;
+    use crate::test_util::Lcg;
+
+    // Instructions with random operations and rectangles inside a grid of
+    // the given size, from a simple linear congruential generator.
+    fn random_instructions(count: usize, size: i64, seed: u64) -> Vec<Instruction> {
+        let mut rng = Lcg::new(seed);
+        let mut next = |below: i64| rng.below(below as u64) as i64;
+
+        let mut instructions = Vec::new();
+        for n in 0..count {
+            let op = match next(3) {
+                0 => Operation::ON,
+                1 => Operation::OFF,
+                _ => Operation::TOGGLE,
+            };
+            let (xa, xb) = (next(size), next(size));
+            let (ya, yb) = (next(size), next(size));
+            instructions.push(Instruction {
+                op: op, x1: xa.min(xb), y1: ya.min(yb), x2: xa.max(xb), y2: ya.max(yb), line: n+1 });
+        }
+
+        instructions
+    }
+
+    #[test]
+    fn test_compressed() {
+        for seed in 0..5 {
+            let day = Lights { instructions: random_instructions(50, 1000, seed), grid: Grid::new(1000, 1000) };
+            assert_eq!(day.run_part1_compressed(), day.run_part1());
+            assert_eq!(day.run_part2_compressed(), day.run_part2());
+        }
+
+        let day = Lights { instructions: vec![], grid: Grid::new(1000, 1000) };
+        assert_eq!(day.run_part1_compressed(), Ok(0));
+    }
+
+    #[test]
+    fn test_compressed_operations() {
+        // With invert the brightness models fall back to applying operations
+        // one at a time; without it every model but Rgb composes them.
+        for (seed, invert) in [(0, false), (1, false), (2, true), (3, true)] {
+            let mut rng = Lcg::new(seed + 100);
+            let mut instructions = random_instructions(60, 200, seed);
+            for i in &mut instructions {
+                i.op = match rng.below(if invert { 7 } else { 6 }) {
+                    0 => Operation::ON,
+                    1 => Operation::COLOUR(100, 0, 200),
+                    2 => Operation::OFF,
+                    3 => Operation::TOGGLE,
+                    4 => Operation::SET(rng.below(6)),
+                    5 => Operation::DIM(rng.below(3)),
+                    _ => Operation::INVERT,
+                };
+            }
+            let day = Lights { instructions, grid: Grid::new(200, 200) };
+
+            assert_eq!(day.run_compressed(&OnOff), day.run(&OnOff));
+            assert_eq!(day.run_compressed(&Brightness), day.run(&Brightness));
+            assert_eq!(day.run_compressed(&CappedDimmer { max: 3 }), day.run(&CappedDimmer { max: 3 }));
+            assert_eq!(day.run_compressed(&Rgb { colour: (50, 60, 70) }), day.run(&Rgb { colour: (50, 60, 70) }));
+        }
+    }
+
+    #[test]
+    fn test_maps() {
+        let ops = [Operation::ON, Operation::OFF, Operation::TOGGLE, Operation::SET(4),
+                   Operation::SET(0), Operation::DIM(2), Operation::DIM(0)];
+        for first in &ops {
+            for second in &ops {
+                for start in 0..6 {
+                    let mut lit = start % 2 == 1;
+                    OnOff.apply(&mut lit, first);
+                    OnOff.apply(&mut lit, second);
+                    let map = OnOff.map(first).unwrap().then(&OnOff.map(second).unwrap());
+                    assert_eq!(map.apply(&(start % 2 == 1)), lit);
+
+                    let capped = CappedDimmer { max: 5 };
+                    let mut level = start;
+                    capped.apply(&mut level, first);
+                    capped.apply(&mut level, second);
+                    let map = capped.map(first).unwrap().then(&capped.map(second).unwrap());
+                    assert_eq!(map.apply(&start), level);
+                }
+            }
+        }
+
+        assert_eq!(Brightness.map(&Operation::INVERT), None);
+        assert_eq!(Brightness.map(&Operation::SET(u64::MAX)), None);
+    }
+
+    #[test]
+    fn test_compressed_large() {
+        // A 10^6 x 10^6 grid
+        let mut instructions = random_instructions(300, 1000000, 42);
+        instructions.push(Instruction { op: Operation::ON, x1: 0, y1: 0, x2: 999999, y2: 999999, line: 301 });
+        instructions.push(Instruction { op: Operation::TOGGLE, x1: 0, y1: 0, x2: 499999, y2: 999999, line: 302 });
+        let day = Lights { instructions: instructions, grid: Grid::new(1000000, 1000000) };
+        assert_eq!(day.run_part1_compressed(), Ok(500000000000));
+    }
+
+    // cargo test --release bench_compressed -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_compressed() {
+        use std::time::{Duration, Instant};
+
+        for count in [1000, 2000, 4000] {
+            let day = Lights { instructions: random_instructions(count, 1000000, 7),
+                               grid: Grid::new(1000000, 1000000) };
+
+            let start = Instant::now();
+            let lit = day.run_part1_compressed().unwrap();
+            let part1_time = start.elapsed();
+
+            let start = Instant::now();
+            let brightness = day.run_part2_compressed().unwrap();
+            let part2_time = start.elapsed();
+
+            assert!(lit > 0 && brightness >= lit);
+            println!("{} instructions: part 1 {:?}, part 2 {:?}", count, part1_time, part2_time);
+            assert!(part1_time + part2_time < Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn test_grid() {
+        let instructions = vec![
+            Instruction { op: Operation::ON, x1: -2, y1: -1, x2: 1, y2: 0, line: 1 },
+            Instruction { op: Operation::TOGGLE, x1: 0, y1: 0, x2: 2, y2: 0, line: 2 },
+        ];
+        let mut day = Lights { instructions: instructions, grid: Grid::new(1000, 1000) };
+
+        // Negative coordinates don't fit the default grid
+        assert_eq!(day.run_part1(),
+                   Err("Line 1: -2,-1 through 1,0 is off the 1000x1000 grid starting at 0,0.".to_string()));
+        assert!(day.part1().is_err());
+
+        day.infer_grid();
+        assert_eq!(day.grid, Grid { x_min: -2, y_min: -1, width: 5, height: 2 });
+        assert_eq!(day.run_part1(), Ok(8 - 2 + 1));
+        assert_eq!(day.run_part2(), Ok(8 + 6));
+        assert_eq!(day.run_part1_compressed(), day.run_part1());
+
+        day.set_grid(Grid::new(3, 3));
+        assert!(day.check_bounds().is_err());
+        day.set_grid(Grid { x_min: -10, y_min: -10, width: 20, height: 20 });
+        assert_eq!(day.run_part1(), Ok(7));
+    }
+
+    #[test]
+    fn test_models() {
+        let instructions = vec![
+            Instruction { op: Operation::ON, x1: 0, y1: 0, x2: 1, y2: 1, line: 1 },
+            Instruction { op: Operation::TOGGLE, x1: 1, y1: 1, x2: 2, y2: 2, line: 2 },
+            Instruction { op: Operation::ON, x1: 0, y1: 0, x2: 0, y2: 0, line: 3 },
+        ];
+        let day = Lights { instructions: instructions, grid: Grid::new(3, 3) };
+
+        assert_eq!(day.run(&OnOff), Ok(4 - 1 + 3));
+        assert_eq!(day.run(&Brightness), Ok(4 + 8 + 1));
+        assert_eq!(day.run(&CappedDimmer { max: 1 }), Ok(4 + 3));
+
+        // Light 1,1 is red then inverted to cyan; 0,0 gets red twice.
+        let rgb = Rgb { colour: (200, 0, 0) };
+        assert_eq!(day.run(&rgb), Ok(255 + 200 + 200 + (55 + 255 + 255) + 3 * 765));
+
+        for seed in 0..3 {
+            let day = Lights { instructions: random_instructions(30, 100, seed), grid: Grid::new(100, 100) };
+            assert_eq!(day.run_compressed(&CappedDimmer { max: 3 }), day.run(&CappedDimmer { max: 3 }));
+            assert_eq!(day.run_compressed(&rgb), day.run(&rgb));
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let instructions = vec![
+            Instruction { op: Operation::ON, x1: 0, y1: 0, x2: 8, y2: 0, line: 1 },
+            Instruction { op: Operation::TOGGLE, x1: 0, y1: 0, x2: 1, y2: 1, line: 2 },
+        ];
+        let day = Lights { instructions: instructions, grid: Grid::new(9, 2) };
+
+        // Row 0: off off on on on on on on on, row 1: on on off ...
+        let mut out: Vec<u8> = Vec::new();
+        day.render_pbm(&mut out).unwrap();
+        assert_eq!(out, [b"P4\n9 2\n".to_vec(), vec![0b11000000, 0b00000000, 0b00111111, 0b10000000]].concat());
+
+        // Brightness 3 3 1 1 ..., row 1: 2 2 0 ...
+        let mut out: Vec<u8> = Vec::new();
+        day.render_pgm(&mut out).unwrap();
+        let mut expected = b"P5\n9 2\n255\n".to_vec();
+        expected.extend([255, 255, 85, 85, 85, 85, 85, 85, 85]);
+        expected.extend([170, 170, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_dump_frames() {
+        let dir = std::env::temp_dir().join(format!("lights_frames_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let instructions = vec![
+            Instruction { op: Operation::ON, x1: 0, y1: 0, x2: 1, y2: 1, line: 1 },
+            Instruction { op: Operation::TOGGLE, x1: 0, y1: 0, x2: 0, y2: 0, line: 2 },
+        ];
+        let day = Lights { instructions: instructions, grid: Grid::new(2, 2) };
+
+        assert_eq!(day.dump_frames(&dir, false), Ok(2));
+        assert_eq!(std::fs::read(dir.join("frame_0000.pbm")).unwrap(), b"P4\n2 2\n\x00\x00".to_vec());
+        assert_eq!(std::fs::read(dir.join("frame_0001.pbm")).unwrap(), b"P4\n2 2\n\x80\x00".to_vec());
+
+        assert_eq!(day.dump_frames(&dir, true), Ok(2));
+        assert!(dir.join("frame_0001.pgm").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(day.dump_frames(&dir, false).is_err());
+    }
+
+    #[test]
+    fn test_parse() {
+        let day = Lights::from_str("turn on 0,0 through 999,999\n\
+                                    toggle 0,0 through 999,0\n\
+                                    \n\
+                                    turn off 499,499 through 500,500\n\
+                                    set 7 -3,-4 through 5,6\n\
+                                    dim 2 1,1 through 1,1\n\
+                                    invert 0,0 through 0,0\n\
+                                    turn on 255,0,128 0,0 through 0,0\n").unwrap();
+        let ops: Vec<&Operation> = day.instructions.iter().map(|i| &i.op).collect();
+        assert_eq!(ops, vec![&Operation::ON, &Operation::TOGGLE, &Operation::OFF,
+                             &Operation::SET(7), &Operation::DIM(2), &Operation::INVERT,
+                             &Operation::COLOUR(255, 0, 128)]);
+        assert_eq!(day.instructions[2].line, 4);
+        assert_eq!((day.instructions[3].x1, day.instructions[3].y1), (-3, -4));
+
+        let errors = vec![
+            ("turn of 0,0 through 1,1", "Line 1: unknown operation 'turn of'."),
+            ("toggle 5,0 through 1,1", "Line 1: corners 5,0 through 1,1 are swapped; the first should be the lower."),
+            ("toggle 0,2 through 1,1", "Line 1: corners 0,2 through 1,1 are swapped; the first should be the lower."),
+            ("toggle 0,0 thru 1,1", "Line 1: expected '<operation> x1,y1 through x2,y2', found 'toggle 0,0 thru 1,1'."),
+            ("set high 0,0 through 1,1", "Line 1: 'high' is not a level for set."),
+            ("turn on 256,0,0 0,0 through 1,1", "Line 1: '256,0,0' is not a colour; expected R,G,B from 0 to 255."),
+            ("turn on 1,2 0,0 through 1,1", "Line 1: '1,2' is not a colour; expected R,G,B from 0 to 255."),
+            ("0,0 through 1,1", "Line 1: unknown operation ''."),
+        ];
+        for (text, error) in errors {
+            assert_eq!(Lights::from_str(text).err(), Some(error.to_string()));
+        }
+        assert_eq!(Lights::from_str("toggle 0,0 through 1,1\nflip 0,0 through 1,1").err(),
+                   Some("Line 2: unknown operation 'flip'.".to_string()));
+    }
+
+    #[test]
+    fn test_new_operations() {
+        let mut day = Lights::from_str("set 5 0,0 through 1,1\n\
+                                        dim 2 0,0 through 0,1\n\
+                                        invert 1,0 through 2,0\n").unwrap();
+        day.set_grid(Grid::new(3, 2));
+
+        // Lit after set: 4; dim puts out 0,0 and 0,1; invert flips 1,0 and 2,0
+        assert_eq!(day.run(&OnOff), Ok(2));
+        // 3 3 | 0 5 | 1 0
+        assert_eq!(day.run(&Brightness), Ok(3 + 3 + 5 + 1));
+        // Capped at 4: 2 2 | 0 4 | 4 0
+        assert_eq!(day.run(&CappedDimmer { max: 4 }), Ok(2 + 2 + 4 + 4));
+        assert_eq!(day.run_compressed(&CappedDimmer { max: 4 }), day.run(&CappedDimmer { max: 4 }));
+        // Grey 3 3 | 250 5 | 255 0, three channels each
+        assert_eq!(day.run(&Rgb { colour: (1, 2, 3) }), Ok(3 * (3 + 3 + 250 + 5 + 255)));
+    }
+
+    #[test]
+    fn test_colour_mixing() {
+        let mut day = Lights::from_str("turn on 255,0,0 0,0 through 1,0\n\
+                                        turn on 0,0,255 1,0 through 2,0\n\
+                                        turn on 0,200,0 2,0 through 2,0\n\
+                                        turn on 0,100,0 2,0 through 2,0\n\
+                                        turn on 0,0,0 3,0 through 3,0\n").unwrap();
+        day.set_grid(Grid::new(4, 1));
+
+        // Red | magenta | blue with saturated green | black
+        let rgb = Rgb { colour: (255, 255, 255) };
+        assert_eq!(day.final_state(&rgb).unwrap(),
+                   vec![vec![(255, 0, 0)], vec![(255, 0, 255)], vec![(0, 255, 255)], vec![(0, 0, 0)]]);
+        assert_eq!(day.run(&rgb), Ok(255 + 510 + 510));
+        assert_eq!(day.run_compressed(&rgb), day.run(&rgb));
+
+        // Every colour turns the light on, even black.
+        assert_eq!(day.run(&OnOff), Ok(4));
+        assert_eq!(day.run(&Brightness), Ok(1 + 2 + 3 + 1));
+    }
 
     #[test]
     fn test_example1() {
         let day = Lights::load("data/day6_example1.txt");
-        assert_eq!(day.run_part1(), 1000000);
+        assert_eq!(day.run_part1(), Ok(1000000));
     }
     #[test]
     fn test_example2() {
         let day = Lights::load("data/day6_example2.txt");
-        assert_eq!(day.run_part1(), 1000);
+        assert_eq!(day.run_part1(), Ok(1000));
     }
     #[test]
     fn test_example3() {
         let day = Lights::load("data/day6_example3.txt");
-        assert_eq!(day.run_part1(), 999996);
+        assert_eq!(day.run_part1(), Ok(999996));
     }
 
     #[test]
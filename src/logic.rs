@@ -1,14 +1,31 @@
-use std::collections::HashMap;
+use std::cell::OnceCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, Write};
 use lazy_static::lazy_static;
 use regex::Regex;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Operation {
     UNARY, AND, OR, NOT, LSHIFT, RSHIFT,
 }
 
+impl Operation {
+    // The output for the given inputs.  UNARY and NOT ignore b.
+    fn apply(&self, a: u16, b: u16) -> u16 {
+        match self {
+            Operation::UNARY => a,
+            Operation::AND => a & b,
+            Operation::OR => a | b,
+            Operation::NOT => !a,
+            // Shifting a u16 by 16 or more leaves nothing.
+            Operation::LSHIFT => a.checked_shl(b as u32).unwrap_or(0),
+            Operation::RSHIFT => a.checked_shr(b as u32).unwrap_or(0),
+        }
+    }
+}
+
 // A Gate structure takes one or two inputs, performs an operation and produces
 // an output.  Operands are represented as strings which may be numeric literals
 // or alphabetic names of input nodes.
@@ -16,38 +33,114 @@ struct Gate {
     operation: Operation,
     operand1: String,
     operand2: String,
-    _output: String,
+    output: String,
 }
 
 impl Gate {
-    fn eval(&self, circuit: &Circuit, cache: &mut HashMap<String, u16>) -> u16 {
-        // println!("Evaluating {:?}: {}, {} -> {}",
-        //          self.operation, self.operand1, self.operand2, self._output);
+    // The operands this gate actually reads.
+    fn operands(&self) -> Vec<&str> {
+        match self.operation {
+            Operation::UNARY | Operation::NOT => vec![&self.operand1],
+            _ => vec![&self.operand1, &self.operand2],
+        }
+    }
+}
 
+// Gates print in the input file's syntax, e.g. "x AND y -> z".
+impl fmt::Display for Gate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.operation {
-            Operation::UNARY => {
-                circuit.eval_cached(&self.operand1, cache)
-            }
-            Operation::AND => {
-                circuit.eval_cached(&self.operand1, cache) &
-                    circuit.eval_cached(&self.operand2, cache)
-            }
-            Operation::OR => {
-                circuit.eval_cached(&self.operand1, cache) |
-                    circuit.eval_cached(&self.operand2, cache)
-            }
-            Operation::NOT => {
-                !circuit.eval_cached(&self.operand1, cache)
-            }
-            Operation::LSHIFT => {
-                circuit.eval_cached(&self.operand1, cache) <<
-                    circuit.eval_cached(&self.operand2, cache)
-            }
-            Operation::RSHIFT => {
-                circuit.eval_cached(&self.operand1, cache) >>
-                    circuit.eval_cached(&self.operand2, cache)
+            Operation::UNARY => write!(f, "{} -> {}", self.operand1, self.output),
+            Operation::NOT => write!(f, "NOT {} -> {}", self.operand1, self.output),
+            _ => write!(f, "{} {:?} {} -> {}", self.operand1, self.operation, self.operand2, self.output),
+        }
+    }
+}
+
+// An input to a compiled gate: a wire id or a numeric literal.
+#[derive(Clone, Copy)]
+enum Input {
+    Wire(usize),
+    Value(u16),
+}
+
+// A gate with its wires interned.
+struct Node {
+    operation: Operation,
+    input1: Input,
+    input2: Input,
+    output: usize,
+}
+
+// A circuit compiled for evaluation.  Wire names are interned to ids and the
+// gates are sorted so each comes after the gates that drive its inputs, so
+// the whole circuit evaluates in one pass over a dense array of values.
+pub struct Netlist {
+    ids: HashMap<String, usize>,
+    names: Vec<String>,
+    nodes: Vec<Node>,
+}
+
+impl Netlist {
+    pub fn id(&self, wire: &str) -> Option<usize> {
+        self.ids.get(wire).copied()
+    }
+
+    #[allow(dead_code)]
+    pub fn name(&self, id: usize) -> &str {
+        &self.names[id]
+    }
+
+    #[allow(dead_code)]
+    pub fn wires(&self) -> usize {
+        self.names.len()
+    }
+
+    // The value on every wire, indexed by id.  Pinned wires carry the given
+    // values in place of their gates' outputs.
+    pub fn evaluate(&self, pinned: &[(usize, u16)]) -> Vec<u16> {
+        let mut values: Vec<u16> = vec![0; self.names.len()];
+        let mut fixed: Vec<bool> = vec![false; self.names.len()];
+        for &(id, value) in pinned {
+            values[id] = value;
+            fixed[id] = true;
+        }
+
+        for node in &self.nodes {
+            if fixed[node.output] {
+                continue;
             }
+            let read = |input: Input| match input {
+                Input::Wire(id) => values[id],
+                Input::Value(value) => value,
+            };
+            let value = node.operation.apply(read(node.input1), read(node.input2));
+            values[node.output] = value;
         }
+
+        values
+    }
+}
+
+// An output's value before and after pinning some wires.
+#[derive(Debug, PartialEq)]
+pub struct Change {
+    pub wire: String,
+    pub before: u16,
+    pub after: u16,
+}
+
+// The outputs of a what-if evaluation, in the order they were asked for.
+#[derive(Debug)]
+pub struct WhatIf {
+    pub outputs: Vec<Change>,
+}
+
+#[allow(dead_code)]
+impl WhatIf {
+    // Just the outputs whose values changed.
+    pub fn changed(&self) -> Vec<&Change> {
+        self.outputs.iter().filter(|change| change.before != change.after).collect()
     }
 }
 
@@ -55,10 +148,25 @@ impl Gate {
 pub struct Circuit {
     // Output nodes map to the Gate structures that produces them.
     gates: HashMap<String, Gate>,
+    // The whole circuit compiled and evaluated, worked out on first use.
+    compiled: OnceCell<Result<(Netlist, Vec<u16>), String>>,
 }
 
 impl Circuit {
     pub fn load(filename: &str) -> Circuit {
+        let file = File::open(filename).unwrap();
+        let reader = BufReader::new(file);
+
+        Circuit::from_lines(reader.lines().map(|line| line.unwrap()))
+    }
+
+    // Gates one per line, as in the input file.
+    #[allow(dead_code)]
+    pub fn from_str(s: &str) -> Circuit {
+        Circuit::from_lines(s.lines().map(|line| line.to_string()))
+    }
+
+    fn from_lines<I: Iterator<Item=String>>(lines: I) -> Circuit {
         let mut gates: HashMap<String, Gate> = HashMap::new();
 
         lazy_static! {
@@ -70,11 +178,8 @@ impl Circuit {
             static ref LSHIFT_RE: Regex = Regex::new("([0-9]+|[a-z]+) LSHIFT ([0-9]+|[a-z]+)").unwrap();
             static ref RSHIFT_RE: Regex = Regex::new("([0-9]+|[a-z]+) RSHIFT ([0-9]+|[a-z]+)").unwrap();
         }
-        let file = File::open(filename).unwrap();
-        let reader = BufReader::new(file);
-
-        for line in reader.lines() {
-            let l = &line.unwrap();
+        for line in lines {
+            let l = &line;
             match GATE_RE.captures(l) {
                 Some(cap) => {
                     let expr= &cap[1];
@@ -148,61 +253,432 @@ impl Circuit {
                                  Gate { operation: operation,
                                      operand1: operand1,
                                      operand2: operand2,
-                                     _output: output } );
+                                     output: output } );
                 }
                 _ => {}
             }
         }
 
-        Circuit { gates: gates }
+        Circuit { gates: gates, compiled: OnceCell::new() }
     }
 
-    fn eval_cached(&self, node: &str, cache: &mut HashMap<String, u16>) -> u16 {
-        match cache.get(node) {
-            Some(v) => *v,
-            None => {
-                let newval = match node.parse::<u16>() {
-                    Ok(value) => {
-                        // println!("Evaluated constant: {}", value);
-                        value
-                    },
-                    Err(_) => {
-                        // Find the gate with this node name as output and evaluate it.
-                        match self.gates.get(node) {
-                            Some(gate) => gate.eval(self, cache),
-                            None => panic!("Could not get gate."),
-                        }
+    // Compile the whole circuit.
+    pub fn compile(&self) -> Result<Netlist, String> {
+        let mut outputs: Vec<&str> = self.gates.keys().map(|wire| wire.as_str()).collect();
+        outputs.sort();
+
+        self.compile_for(&outputs)
+    }
+
+    // Compile just the gates the given outputs depend on.  Fails if one of
+    // them reads a wire no gate drives, or if they contain a loop.
+    pub fn compile_for(&self, outputs: &[&str]) -> Result<Netlist, String> {
+        self.compile_pinned(outputs, &[])
+    }
+
+    // Like compile_for, but the pinned wires are inputs to the netlist: the
+    // gates driving them are left out, so they need not exist and loops
+    // through them are broken.  Pinned wires outside the cone are ignored.
+    pub fn compile_pinned(&self, outputs: &[&str], pinned: &[&str]) -> Result<Netlist, String> {
+        let pinned: HashSet<&str> = pinned.iter().copied().collect();
+
+        // Collect the fan-in cone of the outputs, stopping at pinned wires.
+        let mut cone: Vec<&Gate> = Vec::new();
+        let mut inputs: Vec<&str> = Vec::new();
+        let mut seen: HashSet<&str> = HashSet::new();
+        let mut stack: Vec<&str> = Vec::new();
+        for output in outputs.iter().rev() {
+            if output.parse::<u16>().is_err() {
+                if !self.gates.contains_key(*output) && !pinned.contains(output) {
+                    return Err(format!("No gate drives wire {}.", output));
+                }
+                stack.push(output);
+            }
+        }
+        while let Some(wire) = stack.pop() {
+            if !seen.insert(wire) {
+                continue;
+            }
+            if pinned.contains(wire) {
+                inputs.push(wire);
+                continue;
+            }
+            let gate = &self.gates[wire];
+            cone.push(gate);
+            for operand in gate.operands() {
+                if operand.parse::<u16>().is_err() {
+                    if !self.gates.contains_key(operand) && !pinned.contains(operand) {
+                        return Err(format!("Wire {} is read by '{}' but no gate drives it.", operand, gate));
+                    }
+                    stack.push(operand);
+                }
+            }
+        }
+
+        // Gate n in the cone drives wire n; pinned wires come after them.
+        let mut ids: HashMap<String, usize> = HashMap::new();
+        for (id, gate) in cone.iter().enumerate() {
+            ids.insert(gate.output.clone(), id);
+        }
+        for (n, wire) in inputs.iter().enumerate() {
+            ids.insert(wire.to_string(), cone.len() + n);
+        }
+        let input = |operand: &str| match operand.parse::<u16>() {
+            Ok(value) => Input::Value(value),
+            Err(_) => Input::Wire(ids[operand]),
+        };
+        let nodes: Vec<Node> = cone.iter().map(|gate| {
+            let operands = gate.operands();
+            Node {
+                operation: gate.operation,
+                input1: input(operands[0]),
+                input2: if operands.len() > 1 { input(operands[1]) } else { Input::Value(0) },
+                output: ids[&gate.output],
+            }
+        }).collect();
+
+        // Sort topologically: a gate is ready once every wire it reads is.
+        // Pinned wires are always ready.
+        let mut waiting: Vec<usize> = vec![0; nodes.len()];
+        let mut readers: Vec<Vec<usize>> = vec![Vec::new(); nodes.len()];
+        for (n, node) in nodes.iter().enumerate() {
+            for input in [node.input1, node.input2] {
+                if let Input::Wire(id) = input {
+                    if id >= nodes.len() {
+                        continue;
+                    }
+                    waiting[n] += 1;
+                    readers[id].push(n);
+                }
+            }
+        }
+        let mut ready: VecDeque<usize> = (0..nodes.len()).filter(|n| waiting[*n] == 0).collect();
+        let mut order: Vec<usize> = Vec::with_capacity(nodes.len());
+        while let Some(n) = ready.pop_front() {
+            order.push(n);
+            for &reader in &readers[n] {
+                waiting[reader] -= 1;
+                if waiting[reader] == 0 {
+                    ready.push_back(reader);
+                }
+            }
+        }
+
+        // Gates never ready are in a loop or downstream of one.  Each reads
+        // at least one other such gate, so following those reads from an
+        // output eventually comes round to a wire already on the path.
+        if order.len() < nodes.len() {
+            // Where each wire is on the path, if it is
+            let mut path: Vec<usize> = Vec::new();
+            let mut position: Vec<Option<usize>> = vec![None; nodes.len()];
+            let mut wire = outputs.iter()
+                .filter_map(|output| ids.get(*output))
+                .copied()
+                .find(|id| *id < nodes.len() && waiting[*id] > 0)
+                .unwrap();
+            while position[wire].is_none() {
+                position[wire] = Some(path.len());
+                path.push(wire);
+                let node = &nodes[wire];
+                wire = [node.input1, node.input2].iter()
+                    .filter_map(|input| match input {
+                        Input::Wire(id) if *id < nodes.len() && waiting[*id] > 0 => Some(*id),
+                        _ => None,
+                    })
+                    .next()
+                    .unwrap();
+            }
+            let start = position[wire].unwrap();
+            let mut cycle: Vec<&str> = path[start..].iter().map(|id| cone[*id].output.as_str()).collect();
+            cycle.push(&cone[wire].output);
+            return Err(format!("Loop in circuit: {}.", cycle.join(" -> ")));
+        }
+
+        let names: Vec<String> = cone.iter().map(|gate| gate.output.clone())
+            .chain(inputs.iter().map(|wire| wire.to_string()))
+            .collect();
+        let mut nodes: Vec<Option<Node>> = nodes.into_iter().map(Some).collect();
+        let nodes: Vec<Node> = order.iter().map(|n| nodes[*n].take().unwrap()).collect();
+
+        Ok(Netlist { ids: ids, names: names, nodes: nodes })
+    }
+
+    // The whole circuit compiled and evaluated with nothing pinned, worked
+    // out on first use and kept for later lookups, or why it won't compile.
+    fn compiled(&self) -> &Result<(Netlist, Vec<u16>), String> {
+        self.compiled.get_or_init(|| {
+            let netlist = self.compile()?;
+            let values = netlist.evaluate(&[]);
+            Ok((netlist, values))
+        })
+    }
+
+    // The value on a wire, or of a numeric literal.  The whole circuit is
+    // compiled and evaluated once, then looked up.  If it won't compile,
+    // just the wire's cone is tried, which may be sound.
+    fn eval(&self, node: &str) -> Result<u16, String> {
+        if let Ok(value) = node.parse::<u16>() {
+            return Ok(value);
+        }
+        if let Ok((netlist, values)) = self.compiled() {
+            return netlist.id(node).map(|id| values[id]).ok_or(format!("No gate drives wire {}.", node));
+        }
+
+        let netlist = self.compile_for(&[node])?;
+        let values = netlist.evaluate(&[]);
+
+        Ok(values[netlist.id(node).unwrap()])
+    }
+
+    // The values of the outputs with the given wires pinned to values.
+    #[allow(dead_code)]
+    pub fn eval_pinned(&self, pins: &[(&str, u16)], outputs: &[&str]) -> Result<Vec<u16>, String> {
+        let wires: Vec<&str> = pins.iter().map(|(wire, _)| *wire).collect();
+        let netlist = self.compile_pinned(outputs, &wires)?;
+        let pinned: Vec<(usize, u16)> = pins.iter()
+            .filter_map(|(wire, value)| netlist.id(wire).map(|id| (id, *value)))
+            .collect();
+        let values = netlist.evaluate(&pinned);
+
+        Ok(outputs.iter().map(|output| match output.parse::<u16>() {
+            Ok(value) => value,
+            Err(_) => values[netlist.id(output).unwrap()],
+        }).collect())
+    }
+
+    // Evaluate the outputs with and without the pins, to see what they
+    // change.
+    #[allow(dead_code)]
+    pub fn what_if(&self, pins: &[(&str, u16)], outputs: &[&str]) -> Result<WhatIf, String> {
+        let before = self.eval_pinned(&[], outputs)?;
+        let after = self.eval_pinned(pins, outputs)?;
+
+        let outputs = outputs.iter().zip(before.iter().zip(after.iter()))
+            .map(|(wire, (before, after))| Change { wire: wire.to_string(), before: *before, after: *after })
+            .collect();
+        Ok(WhatIf { outputs: outputs })
+    }
+
+    // The wires a wire depends on, itself included.  Undriven wires are
+    // included but not followed, and loops are tolerated.
+    fn fan_in<'a>(&'a self, wire: &'a str) -> HashSet<&'a str> {
+        let mut cone: HashSet<&str> = HashSet::new();
+        let mut stack: Vec<&str> = vec![wire];
+        while let Some(wire) = stack.pop() {
+            if wire.parse::<u16>().is_ok() || !cone.insert(wire) {
+                continue;
+            }
+            if let Some(gate) = self.gates.get(wire) {
+                stack.extend(gate.operands());
+            }
+        }
+
+        cone
+    }
+
+    // Graphviz DOT for the circuit: wires are ellipses and gates are boxes
+    // labelled with their operation and any literal operands.  With annotate,
+    // each wire shows its signal too, and with highlight the gates and wires
+    // feeding that wire are drawn in red.
+    #[allow(dead_code)]
+    pub fn to_dot(&self, annotate: bool, highlight: Option<&str>) -> Result<String, String> {
+        let netlist = if annotate { Some(self.compile()?) } else { None };
+        let values = netlist.as_ref().map(|netlist| netlist.evaluate(&[]));
+        let cone = match highlight {
+            Some(wire) => self.fan_in(wire),
+            None => HashSet::new(),
+        };
+        let red = |wire: &str| if cone.contains(wire) { ", color=red, penwidth=2" } else { "" };
+        let red_edge = |wire: &str| if cone.contains(wire) { " [color=red, penwidth=2]" } else { "" };
+
+        let mut gates: Vec<&Gate> = self.gates.values().collect();
+        gates.sort_by(|a, b| a.output.cmp(&b.output));
+        let mut wires: Vec<&str> = gates.iter()
+            .flat_map(|gate| gate.operands().into_iter().chain([gate.output.as_str()]))
+            .filter(|wire| wire.parse::<u16>().is_err())
+            .collect();
+        wires.sort();
+        wires.dedup();
+
+        let mut dot = String::new();
+        dot.push_str("digraph circuit {\n");
+        dot.push_str("    rankdir=LR;\n");
+        for wire in wires {
+            let label = match (&netlist, &values) {
+                (Some(netlist), Some(values)) => format!("{}\\n{}", wire, values[netlist.id(wire).unwrap()]),
+                _ => wire.to_string(),
+            };
+            // Wires no gate drives are dashed.
+            let style = if self.gates.contains_key(wire) { "" } else { ", style=dashed" };
+            dot.push_str(&format!("    \"{}\" [label=\"{}\"{}{}];\n", wire, label, style, red(wire)));
+        }
+        for gate in gates {
+            let mut label = format!("{:?}", gate.operation);
+            for operand in gate.operands() {
+                if operand.parse::<u16>().is_ok() {
+                    label.push_str(&format!(" {}", operand));
+                }
+            }
+            dot.push_str(&format!("    \"{} gate\" [shape=box, label=\"{}\"{}];\n",
+                                  gate.output, label, red(&gate.output)));
+            for operand in gate.operands() {
+                if operand.parse::<u16>().is_err() {
+                    dot.push_str(&format!("    \"{}\" -> \"{} gate\"{};\n",
+                                          operand, gate.output, red_edge(&gate.output)));
+                }
+            }
+            dot.push_str(&format!("    \"{} gate\" -> \"{}\"{};\n",
+                                  gate.output, gate.output, red_edge(&gate.output)));
+        }
+        dot.push_str("}\n");
+
+        Ok(dot)
+    }
+
+    // A smaller circuit computing the same output: literal expressions are
+    // folded, pass-through wires are collapsed and gates the output doesn't
+    // depend on are removed.  Kept wires are left in place with their
+    // gates, so they can still be pinned.
+    #[allow(dead_code)]
+    pub fn simplify(&self, output: &str, keep: &[&str]) -> Result<Circuit, String> {
+        if output.parse::<u16>().is_ok() {
+            return Err(format!("{} is not a wire.", output));
+        }
+        let netlist = self.compile_for(&[output])?;
+        let keep: HashSet<&str> = keep.iter().copied().collect();
+
+        // What each wire reduces to: a literal, or the wire now carrying its
+        // signal.  Gates that don't reduce are kept, in evaluation order.
+        let mut reduced: Vec<Input> = (0..netlist.wires()).map(Input::Wire).collect();
+        let mut kept: Vec<Node> = Vec::new();
+        for node in &netlist.nodes {
+            let resolve = |input: Input| match input {
+                Input::Wire(id) => reduced[id],
+                value => value,
+            };
+            let (a, b) = (resolve(node.input1), resolve(node.input2));
+            let result = match (node.operation, a, b) {
+                _ if keep.contains(netlist.name(node.output)) => None,
+                (operation, Input::Value(a), Input::Value(b)) => Some(Input::Value(operation.apply(a, b))),
+                (Operation::UNARY, a, _) => Some(a),
+                (Operation::AND, Input::Value(0), _) | (Operation::AND, _, Input::Value(0)) => Some(Input::Value(0)),
+                (Operation::AND, Input::Value(0xffff), x) | (Operation::AND, x, Input::Value(0xffff)) => Some(x),
+                (Operation::OR, Input::Value(0xffff), _) | (Operation::OR, _, Input::Value(0xffff)) => Some(Input::Value(0xffff)),
+                (Operation::OR, Input::Value(0), x) | (Operation::OR, x, Input::Value(0)) => Some(x),
+                (Operation::LSHIFT, x, Input::Value(0)) | (Operation::RSHIFT, x, Input::Value(0)) => Some(x),
+                (Operation::LSHIFT, _, Input::Value(n)) | (Operation::RSHIFT, _, Input::Value(n)) if n >= 16 => Some(Input::Value(0)),
+                (Operation::LSHIFT, Input::Value(0), _) | (Operation::RSHIFT, Input::Value(0), _) => Some(Input::Value(0)),
+                _ => None,
+            };
+            match result {
+                Some(input) => reduced[node.output] = input,
+                None => kept.push(Node { operation: node.operation, input1: a, input2: b, output: node.output }),
+            }
+        }
+
+        // Keep only the gates the output still reads from.
+        let mut driver: Vec<Option<usize>> = vec![None; netlist.wires()];
+        for (n, node) in kept.iter().enumerate() {
+            driver[node.output] = Some(n);
+        }
+        let id = netlist.id(output).unwrap();
+        let mut live: Vec<bool> = vec![false; kept.len()];
+        let mut stack: Vec<Input> = vec![reduced[id]];
+        while let Some(input) = stack.pop() {
+            if let Input::Wire(wire) = input {
+                if let Some(n) = driver[wire] {
+                    if !live[n] {
+                        live[n] = true;
+                        stack.push(kept[n].input1);
+                        stack.push(kept[n].input2);
                     }
-                };
+                }
+            }
+        }
 
-                cache.insert(node.to_string(), newval);
-                newval
+        let operand = |input: Input| match input {
+            Input::Wire(id) => netlist.name(id).to_string(),
+            Input::Value(value) => value.to_string(),
+        };
+        let mut gates: HashMap<String, Gate> = HashMap::new();
+        for (n, node) in kept.iter().enumerate() {
+            if live[n] {
+                let unary = node.operation == Operation::UNARY || node.operation == Operation::NOT;
+                gates.insert(netlist.name(node.output).to_string(),
+                             Gate { operation: node.operation,
+                                 operand1: operand(node.input1),
+                                 operand2: if unary { String::from("") } else { operand(node.input2) },
+                                 output: netlist.name(node.output).to_string() });
             }
         }
+        // The output itself may have reduced to a literal or another wire.
+        if !matches!(reduced[id], Input::Wire(wire) if wire == id) {
+            gates.insert(output.to_string(),
+                         Gate { operation: Operation::UNARY,
+                             operand1: operand(reduced[id]),
+                             operand2: String::from(""),
+                             output: output.to_string() });
+        }
+
+        Ok(Circuit { gates: gates, compiled: OnceCell::new() })
+    }
+
+    // Write the circuit in the input file's syntax, one gate per line in
+    // order of output wire.
+    #[allow(dead_code)]
+    pub fn write<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let mut gates: Vec<&Gate> = self.gates.values().collect();
+        gates.sort_by(|a, b| a.output.cmp(&b.output));
+        for gate in gates {
+            writeln!(out, "{}", gate)?;
+        }
+
+        Ok(())
     }
 
-    fn eval(&self, node: &str) -> u16 {
-        let mut cache: HashMap<String, u16> = HashMap::new();
+    #[allow(dead_code)]
+    pub fn write_dot<W: Write>(&self, mut out: W, annotate: bool, highlight: Option<&str>) -> Result<(), String> {
+        let dot = self.to_dot(annotate, highlight)?;
 
-        self.eval_cached(node, &mut cache)
+        out.write_all(dot.as_bytes()).map_err(|e| e.to_string())
     }
 }
 
 impl super::Day for Circuit {
     fn part1(&mut self) -> Result<i64, &str> {
-        return Ok(self.eval("a") as i64);
+        match self.eval("a") {
+            Ok(a) => return Ok(a as i64),
+            Err(_) => return Err("Wire a could not be evaluated."),
+        }
     }
 
     fn part2(&mut self) -> Result<i64, &str> {
         // "Take the signal you got on wire a..."
-        let initial_a = self.eval("a");
+        let initial_a = match self.eval("a") {
+            Ok(a) => a,
+            Err(_) => return Err("Wire a could not be evaluated."),
+        };
+
+        // The whole circuit's netlist is reused if it compiled; if not, as
+        // in eval(), just a's cone is tried, with b pinned.
+        let cone;
+        let netlist = match self.compiled() {
+            Ok((netlist, _)) => netlist,
+            Err(_) => {
+                cone = self.compile_pinned(&["a"], &["b"]);
+                match &cone {
+                    Ok(netlist) => netlist,
+                    Err(_) => return Err("Wire a could not be evaluated."),
+                }
+            }
+        };
+        let a = netlist.id("a").unwrap();
 
         // "Override wire b to that signal..."
-        let mut cache: HashMap<String, u16> = HashMap::new();
-        cache.insert("b".to_string(), initial_a);
+        let pinned: Vec<(usize, u16)> = netlist.id("b").map(|b| (b, initial_a)).into_iter().collect();
 
         // "What new signal is ultimately provided to wire a?"
-        let ultimate_a = self.eval_cached("a", &mut cache);
+        let ultimate_a = netlist.evaluate(&pinned)[a];
 
         return Ok(ultimate_a as i64);
     }
@@ -212,6 +688,225 @@ impl super::Day for Circuit {
 mod tests {
     use super::*;
     use crate::Day;
+    use crate::test_util::Lcg;
+
+    // Name wire n with letters only, as the input format requires.
+    fn wire_name(n: usize) -> String {
+        let mut name = String::new();
+        let mut n = n;
+        loop {
+            name.push((b'a' + (n % 26) as u8) as char);
+            n /= 26;
+            if n == 0 {
+                break;
+            }
+        }
+
+        name
+    }
+
+    // A random circuit of the given number of gates with its lines shuffled,
+    // and the value expected on each wire.  Every gate reads the wire before
+    // it, so the circuit is one long chain.
+    fn random_circuit(gates: usize, seed: u64) -> (String, Vec<(String, u16)>) {
+        let mut rng = Lcg::new(seed);
+        let mut next = |below: usize| rng.below(below as u64) as usize;
+
+        let mut lines: Vec<String> = vec![format!("{} -> {}", next(65536), wire_name(0))];
+        let mut values: Vec<u16> = vec![lines[0].split(' ').next().unwrap().parse().unwrap()];
+        for n in 1..gates {
+            let (prev, other, shift) = (n-1, next(n), next(16));
+            let (line, value) = match next(6) {
+                0 => (format!("{} -> {}", wire_name(prev), wire_name(n)), values[prev]),
+                1 => (format!("{} AND {} -> {}", wire_name(prev), wire_name(other), wire_name(n)),
+                      values[prev] & values[other]),
+                2 => (format!("{} OR {} -> {}", wire_name(prev), wire_name(other), wire_name(n)),
+                      values[prev] | values[other]),
+                3 => (format!("NOT {} -> {}", wire_name(prev), wire_name(n)), !values[prev]),
+                4 => (format!("{} LSHIFT {} -> {}", wire_name(prev), shift, wire_name(n)),
+                      values[prev] << shift),
+                _ => (format!("{} RSHIFT {} -> {}", wire_name(prev), shift, wire_name(n)),
+                      values[prev] >> shift),
+            };
+            lines.push(line);
+            values.push(value);
+        }
+        for n in (1..lines.len()).rev() {
+            lines.swap(n, next(n+1));
+        }
+
+        let expected = values.iter().enumerate().map(|(n, value)| (wire_name(n), *value)).collect();
+        (lines.join("\n"), expected)
+    }
+
+    #[test]
+    fn test_compile() {
+        let (text, expected) = random_circuit(5000, 7);
+        let ckt = Circuit::from_str(&text);
+        let netlist = ckt.compile().unwrap();
+        assert_eq!(netlist.wires(), 5000);
+
+        let values = netlist.evaluate(&[]);
+        for (wire, value) in expected {
+            assert_eq!(values[netlist.id(&wire).unwrap()], value);
+        }
+
+        // Only the cone of a wire is compiled.
+        let netlist = ckt.compile_for(&["ab"]).unwrap();
+        assert_eq!(netlist.wires(), 27);
+        assert_eq!(netlist.name(netlist.id("ab").unwrap()), "ab");
+        assert_eq!(netlist.id("ac"), None);
+    }
+
+    // cargo test --release bench_compile -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_compile() {
+        use std::time::Instant;
+
+        let (text, expected) = random_circuit(1000000, 1);
+        let ckt = Circuit::from_str(&text);
+
+        let start = Instant::now();
+        let netlist = ckt.compile().unwrap();
+        let compile_time = start.elapsed();
+
+        let start = Instant::now();
+        let values = netlist.evaluate(&[]);
+        let eval_time = start.elapsed();
+
+        let (last, value) = &expected[expected.len()-1];
+        assert_eq!(values[netlist.id(last).unwrap()], *value);
+        println!("compile: {:?}, evaluate: {:?}", compile_time, eval_time);
+    }
+
+    #[test]
+    fn test_dot() {
+        let ckt = Circuit::from_str("123 -> x\n456 -> y\nx AND y -> d\nx LSHIFT 2 -> f\n");
+
+        assert_eq!(ckt.to_dot(false, None).unwrap(), "\
+digraph circuit {
+    rankdir=LR;
+    \"d\" [label=\"d\"];
+    \"f\" [label=\"f\"];
+    \"x\" [label=\"x\"];
+    \"y\" [label=\"y\"];
+    \"d gate\" [shape=box, label=\"AND\"];
+    \"x\" -> \"d gate\";
+    \"y\" -> \"d gate\";
+    \"d gate\" -> \"d\";
+    \"f gate\" [shape=box, label=\"LSHIFT 2\"];
+    \"x\" -> \"f gate\";
+    \"f gate\" -> \"f\";
+    \"x gate\" [shape=box, label=\"UNARY 123\"];
+    \"x gate\" -> \"x\";
+    \"y gate\" [shape=box, label=\"UNARY 456\"];
+    \"y gate\" -> \"y\";
+}
+");
+
+        // Values on the wires, and the cone of f in red.
+        let dot = ckt.to_dot(true, Some("f")).unwrap();
+        assert!(dot.contains("    \"d\" [label=\"d\\n72\"];\n"));
+        assert!(dot.contains("    \"f\" [label=\"f\\n492\", color=red, penwidth=2];\n"));
+        assert!(dot.contains("    \"x\" [label=\"x\\n123\", color=red, penwidth=2];\n"));
+        assert!(dot.contains("    \"x\" -> \"f gate\" [color=red, penwidth=2];\n"));
+        assert!(dot.contains("    \"x\" -> \"d gate\";\n"));
+        assert!(dot.contains("    \"y gate\" [shape=box, label=\"UNARY 456\"];\n"));
+
+        // Undriven wires are drawn dashed, but can't be annotated.
+        let ckt = Circuit::from_str("1 -> x\nx OR q -> z\n");
+        assert!(ckt.to_dot(false, Some("z")).unwrap().contains("    \"q\" [label=\"q\", style=dashed, color=red, penwidth=2];\n"));
+        assert_eq!(ckt.to_dot(true, None), Err("Wire q is read by 'x OR q -> z' but no gate drives it.".to_string()));
+    }
+
+    #[test]
+    fn test_what_if() {
+        let ckt = Circuit::from_str("123 -> x\n456 -> y\nx AND y -> d\nx OR y -> e\n\
+                                     x LSHIFT 2 -> f\ny RSHIFT 2 -> g\nNOT x -> h\n");
+
+        assert_eq!(ckt.eval_pinned(&[("x", 0)], &["d", "e", "h", "7"]), Ok(vec![0, 456, 65535, 7]));
+        // Pinned outputs read back their pins; pins outside the cone do nothing.
+        assert_eq!(ckt.eval_pinned(&[("d", 9), ("q", 1)], &["d", "g"]), Ok(vec![9, 114]));
+
+        let what_if = ckt.what_if(&[("y", 123)], &["d", "e", "f", "g"]).unwrap();
+        assert_eq!(what_if.outputs.len(), 4);
+        assert_eq!(what_if.changed(), vec![
+            &Change { wire: "d".to_string(), before: 72, after: 123 },
+            &Change { wire: "e".to_string(), before: 507, after: 123 },
+            &Change { wire: "g".to_string(), before: 114, after: 30 },
+        ]);
+
+        // Pinning breaks loops and stands in for missing drivers, but the
+        // unpinned baseline still needs them.
+        let ckt = Circuit::from_str("c AND 3 -> a\na -> c\nq OR a -> z\n");
+        assert_eq!(ckt.eval_pinned(&[("c", 6), ("q", 8)], &["z", "a"]), Ok(vec![10, 2]));
+        assert_eq!(ckt.what_if(&[("c", 6), ("q", 8)], &["z"]).err(),
+                   Some("Wire q is read by 'q OR a -> z' but no gate drives it.".to_string()));
+        assert_eq!(ckt.eval_pinned(&[("q", 8)], &["z"]).err(),
+                   Some("Loop in circuit: a -> c -> a.".to_string()));
+    }
+
+    #[test]
+    fn test_simplify() {
+        let ckt = Circuit::from_str("123 -> x\nx -> p\np -> r\nr AND 0 -> u\nr OR u -> s\ns LSHIFT 0 -> t\n\
+                                     456 -> y\ny -> yy\nt AND yy -> out\nNOT y -> unused\n");
+        let text = |ckt: &Circuit| {
+            let mut out: Vec<u8> = Vec::new();
+            ckt.write(&mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        assert_eq!(text(&ckt.simplify("out", &[]).unwrap()), "72 -> out\n");
+        assert_eq!(text(&ckt.simplify("out", &["x"]).unwrap()), "x AND 456 -> out\n123 -> x\n");
+        assert_eq!(text(&ckt.simplify("t", &["x"]).unwrap()), "x -> t\n123 -> x\n");
+        assert_eq!(text(&ckt.simplify("x", &["x"]).unwrap()), "123 -> x\n");
+
+        // The simplified circuit still answers what-if questions about kept wires.
+        let simple = ckt.simplify("out", &["x", "y"]).unwrap();
+        assert_eq!(simple.gates.len(), 3);
+        for value in [0, 1, 77, 65535] {
+            assert_eq!(simple.eval_pinned(&[("x", value)], &["out"]), ckt.eval_pinned(&[("x", value)], &["out"]));
+        }
+
+        // Round trip through the text format
+        assert_eq!(text(&Circuit::from_str(&text(&simple))), text(&simple));
+
+        assert_eq!(ckt.simplify("7", &[]).err(), Some("7 is not a wire.".to_string()));
+        assert_eq!(ckt.simplify("q", &[]).err(), Some("No gate drives wire q.".to_string()));
+    }
+
+    #[test]
+    fn test_simplify_input() {
+        let ckt = Circuit::load("data/day7_input.txt");
+        let simple = ckt.simplify("a", &["b"]).unwrap();
+
+        assert!(simple.gates.len() < ckt.gates.len());
+        assert_eq!(simple.eval("a"), Ok(46065));
+        assert_eq!(simple.eval_pinned(&[("b", 46065)], &["a"]), Ok(vec![14134]));
+    }
+
+    #[test]
+    fn test_compiled_once() {
+        let mut ckt = Circuit::from_str("3 -> b\nb LSHIFT 1 -> a\nNOT a -> c\n");
+
+        assert!(ckt.compiled.get().is_none());
+        assert_eq!(ckt.eval("a"), Ok(6));
+        assert!(ckt.compiled.get().is_some());
+        assert_eq!(ckt.eval("c"), Ok(!6));
+        assert_eq!(ckt.eval("q"), Err("No gate drives wire q.".to_string()));
+
+        // b is pinned to 6, so a becomes 12.
+        assert_eq!(ckt.part1(), Ok(6));
+        assert_eq!(ckt.part2(), Ok(12));
+
+        // A loop away from a stops the whole circuit compiling, but not
+        // either part.
+        let mut ckt = Circuit::from_str("3 -> b\nb LSHIFT 1 -> a\n1 -> x\nx AND y -> y\n");
+        assert!(ckt.compiled().is_err());
+        assert_eq!(ckt.part1(), Ok(6));
+        assert_eq!(ckt.part2(), Ok(12));
+    }
 
     #[test]
     fn test_load() {
@@ -236,10 +931,35 @@ mod tests {
             ("y", 456),
         ];
         for (node, expected) in cases {
-            assert_eq!(ckt.eval(node), expected);
+            assert_eq!(ckt.eval(node), Ok(expected));
         }
     }
 
+    #[test]
+    fn test_display() {
+        let ckt = Circuit::from_str("123 -> x\nx AND y -> d\np LSHIFT 2 -> q\nNOT x -> h\n");
+
+        let mut lines: Vec<String> = ckt.gates.values().map(|gate| gate.to_string()).collect();
+        lines.sort();
+        assert_eq!(lines, vec!["123 -> x", "NOT x -> h", "p LSHIFT 2 -> q", "x AND y -> d"]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let ckt = Circuit::from_str("1 -> b\nb AND a -> c\nc -> d\nd OR b -> a\n\
+                                     1 -> x\nx AND q -> z\nz -> w\n");
+
+        assert_eq!(ckt.eval("c"), Err("Loop in circuit: c -> a -> d -> c.".to_string()));
+        assert_eq!(ckt.eval("a"), Err("Loop in circuit: a -> d -> c -> a.".to_string()));
+        assert_eq!(ckt.eval("w"), Err("Wire q is read by 'x AND q -> z' but no gate drives it.".to_string()));
+        assert_eq!(ckt.eval("q"), Err("No gate drives wire q.".to_string()));
+        assert_eq!(ckt.eval("b"), Ok(1));
+
+        // A wire that feeds itself directly
+        let ckt = Circuit::from_str("NOT a -> a\n");
+        assert_eq!(ckt.eval("a"), Err("Loop in circuit: a -> a.".to_string()));
+    }
+
     #[test]
     fn test_part1() {
         let mut day = Circuit::load("data/day7_input.txt");
@@ -17,6 +17,8 @@ mod day16;
 mod day17;
 mod day18;
 mod day19;
+#[cfg(test)]
+mod test_util;
 
 use nql::NotQuiteLisp;
 use presents::Presents;
@@ -1,43 +1,359 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
 use md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+// Nonces a worker thread claims at a time.
+const CHUNK: u32 = 10000;
+
+// Nonces between progress reports from search().
+const PROGRESS_EVERY: u32 = 100000;
+
+// Nonces between checkpoints written by search_resumable().
+const CHECKPOINT_EVERY: u32 = 1000000;
+
+// Hash functions the miner can search with.
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Algorithm { MD5, SHA1, SHA256, }
+
+// Hasher state after consuming the prefix, cloned for each nonce.
+#[derive(Clone)]
+enum HashState {
+    MD5(md5::Context),
+    SHA1(Sha1),
+    SHA256(Sha256),
+}
+
+impl HashState {
+    fn new(algorithm: Algorithm, prefix: &[u8]) -> HashState {
+        match algorithm {
+            Algorithm::MD5 => {
+                let mut context = md5::Context::new();
+                context.consume(prefix);
+                HashState::MD5(context)
+            }
+            Algorithm::SHA1 => HashState::SHA1(Sha1::new_with_prefix(prefix)),
+            Algorithm::SHA256 => HashState::SHA256(Sha256::new_with_prefix(prefix)),
+        }
+    }
+
+    // Digest of the prefix followed by nonce, written into the start of out.
+    // Returns the digest length.
+    fn digest(&self, nonce: &[u8], out: &mut [u8; 32]) -> usize {
+        match self {
+            HashState::MD5(base) => {
+                let mut context = base.clone();
+                context.consume(nonce);
+                out[..16].copy_from_slice(&context.compute().0);
+                16
+            }
+            HashState::SHA1(base) => {
+                let mut hasher = base.clone();
+                hasher.update(nonce);
+                out[..20].copy_from_slice(&hasher.finalize());
+                20
+            }
+            HashState::SHA256(base) => {
+                let mut hasher = base.clone();
+                hasher.update(nonce);
+                out.copy_from_slice(&hasher.finalize());
+                32
+            }
+        }
+    }
+}
+
+// Predicate: the digest starts with at least `bits` zero bits.
+pub fn zero_bits(bits: u32) -> impl Fn(&[u8]) -> bool {
+    move |digest| Mining::leading_zero_bits(digest) >= bits
+}
+
+// Predicate: the digest's hex representation starts with prefix.
+#[allow(dead_code)]
+pub fn hex_prefix(prefix: &str) -> impl Fn(&[u8]) -> bool {
+    // Characters that aren't hex digits can never match.
+    let nibbles: Vec<u32> = prefix.chars().map(|c| c.to_digit(16).unwrap_or(16)).collect();
+
+    move |digest| {
+        nibbles.len() <= 2 * digest.len() &&
+            nibbles.iter().enumerate().all(|(n, nibble)| {
+                let b = digest[n / 2] as u32;
+                let digit = if n % 2 == 0 { b >> 4 } else { b & 0xf };
+                digit == *nibble
+            })
+    }
+}
+
+// Predicate: the digest, read as a big-endian number, is below target.
+// target should be as long as the digest.
+#[allow(dead_code)]
+pub fn below_target(target: Vec<u8>) -> impl Fn(&[u8]) -> bool {
+    move |digest| digest < &target[..]
+}
 
 pub struct Mining {
     prefix: String,
+    algorithm: Algorithm,
+    base: HashState,
 }
 
 impl Mining {
     pub fn new(s: &str) -> Mining {
-        Mining { prefix: s.to_string() }
+        return Mining::with_algorithm(s, Algorithm::MD5);
     }
 
-    fn get_soln(&self, zeros: usize) -> u32 {
-        let mut n = 0;
-        let target_prefix = "0".repeat(zeros);
+    pub fn with_algorithm(s: &str, algorithm: Algorithm) -> Mining {
+        Mining { prefix: s.to_string(), algorithm: algorithm, base: HashState::new(algorithm, s.as_bytes()) }
+    }
+
+    // Decimal digits of n, written into the end of buf.
+    fn nonce_digits(mut n: u32, buf: &mut [u8; 10]) -> &[u8] {
+        let mut start = buf.len();
         loop {
-            // Construct string to hash
-            let s = format!("{}{}", self.prefix,n.to_string());
+            start -= 1;
+            buf[start] = b'0' + (n % 10) as u8;
+            n /= 10;
+            if n == 0 {
+                break;
+            }
+        }
 
-            // Compute hash
-            let digest = md5::compute(s);
-            // println!("{}: {:x}", n, digest);
+        return &buf[start..];
+    }
 
-            // Check for solution criteria
-            if format!("{:x}", digest)[0..zeros] == target_prefix {
-                // Stop the search and return n
-                break n
+    fn leading_zero_bits(digest: &[u8]) -> u32 {
+        let mut bits = 0;
+        for b in digest {
+            bits += b.leading_zeros();
+            if *b != 0 {
+                break;
             }
+        }
+
+        return bits;
+    }
 
-            n += 1;
+    // Check whether the hash of prefix and n satisfies predicate.  Works on
+    // the digest bytes directly, without allocating.
+    fn is_match<P: Fn(&[u8]) -> bool>(&self, n: u32, predicate: &P) -> bool {
+        let mut buf = [0u8; 10];
+        let mut out = [0u8; 32];
+        let len = self.base.digest(Mining::nonce_digits(n, &mut buf), &mut out);
+
+        return predicate(&out[..len]);
+    }
+
+    // Check whether the hash of prefix and n starts with at least `bits` zero
+    // bits.
+    fn is_soln(&self, n: u32, bits: u32) -> bool {
+        return self.is_match(n, &zero_bits(bits));
+    }
+
+    // Lowest nonce whose hash starts with `bits` zero bits.  Each zero hex
+    // digit is four zero bits.  None if no nonce up to u32::MAX works.
+    #[allow(dead_code)]
+    pub fn get_soln_bits(&self, bits: u32) -> Option<u32> {
+        return (0..=u32::MAX).find(|n| self.is_soln(*n, bits));
+    }
+
+    #[cfg(test)]
+    fn get_soln(&self, zeros: usize) -> Option<u32> {
+        return self.get_soln_bits(4 * zeros as u32);
+    }
+
+    pub fn get_soln_parallel(&self, zeros: usize, threads: usize) -> Option<u32> {
+        return self.get_soln_parallel_bits(4 * zeros as u32, threads);
+    }
+
+    // Same answer as get_soln_bits(), searched by the given number of threads.
+    // Threads claim chunks of nonces in increasing order and stop once every
+    // chunk below the best solution so far has been checked, so the smallest
+    // solution is found whatever the scheduling.  None if no nonce works.
+    pub fn get_soln_parallel_bits(&self, bits: u32, threads: usize) -> Option<u32> {
+        return self.find_first_parallel(zero_bits(bits), threads);
+    }
+
+    // Lowest nonce whose hash satisfies predicate, searched by the given
+    // number of threads; None if there is none.
+    pub fn find_first_parallel<P: Fn(&[u8]) -> bool + Sync>(&self, predicate: P, threads: usize) -> Option<u32> {
+        return self.first_parallel_in(0, u32::MAX as u64 + 1, &predicate, threads);
+    }
+
+    // Lowest matching nonce in [start, end).  Chunks are counted in u64 so
+    // claiming the chunks past u32::MAX can't wrap back to 0.
+    fn first_parallel_in<P: Fn(&[u8]) -> bool + Sync>(&self, start: u64, end: u64,
+                                                       predicate: &P, threads: usize) -> Option<u32> {
+        let next_chunk = AtomicU64::new(start);
+        let best = AtomicU64::new(end);
+
+        thread::scope(|scope| {
+            for _ in 0..threads.max(1) {
+                scope.spawn(|| {
+                    loop {
+                        let start = next_chunk.fetch_add(CHUNK as u64, Ordering::SeqCst);
+                        if start >= best.load(Ordering::SeqCst) {
+                            break;
+                        }
+
+                        for n in start..(start + CHUNK as u64).min(end) {
+                            if n >= best.load(Ordering::Relaxed) {
+                                break;
+                            }
+                            if self.is_match(n as u32, predicate) {
+                                best.fetch_min(n, Ordering::SeqCst);
+                                break;
+                            }
+                        }
+                    }
+                });
+            }
+        });
+
+        let best = best.load(Ordering::SeqCst);
+        return if best < end { Some(best as u32) } else { None };
+    }
+
+    // Check nonces in [start, end) for hashes satisfying predicate, such as
+    // zero_bits(20) or hex_prefix("abc"), returning the matches in order;
+    // with first_only the search stops at the first.  progress(n, matches)
+    // is called every PROGRESS_EVERY nonces with the next nonce to be tried
+    // and the matches so far.
+    #[allow(dead_code)]
+    pub fn search<P, F>(&self, predicate: &P, start: u32, end: u32, first_only: bool, mut progress: F) -> Vec<u32>
+        where P: Fn(&[u8]) -> bool, F: FnMut(u32, &[u32]) {
+        let mut matches = Vec::new();
+        for n in start..end {
+            if n > start && (n - start).is_multiple_of(PROGRESS_EVERY) {
+                progress(n, &matches);
+            }
+            if self.is_match(n, predicate) {
+                matches.push(n);
+                if first_only {
+                    break;
+                }
+            }
+        }
+
+        return matches;
+    }
+
+    #[allow(dead_code)]
+    pub fn find_first<P: Fn(&[u8]) -> bool>(&self, predicate: &P, start: u32, end: u32) -> Option<u32> {
+        return self.search(predicate, start, end, true, |_, _| {}).first().cloned();
+    }
+
+    #[allow(dead_code)]
+    pub fn find_all<P: Fn(&[u8]) -> bool>(&self, predicate: &P, start: u32, end: u32) -> Vec<u32> {
+        return self.search(predicate, start, end, false, |_, _| {});
+    }
+
+    // Like search(), but records progress in a checkpoint file so that an
+    // interrupted search carries on where it left off when run again with
+    // the same arguments.  A predicate can't be compared, so the caller
+    // names it with key, such as "zero_bits(20)", on a single line.  The
+    // file holds the prefix on the first line, the search's algorithm, key,
+    // start, end, first_only and next nonce to try on the second and the
+    // matches so far on the third.  progress sees every match so far,
+    // including those from earlier runs.
+    // Takes search()'s arguments, plus the key and the checkpoint.
+    #[allow(dead_code, clippy::too_many_arguments)]
+    pub fn search_resumable<P, F>(&self, predicate: &P, key: &str, start: u32, end: u32, first_only: bool,
+                                  checkpoint: &Path, mut progress: F) -> io::Result<Vec<u32>>
+        where P: Fn(&[u8]) -> bool, F: FnMut(u32, &[u32]) {
+        if key.contains('\n') {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Search key has a newline."));
+        }
+        let search = format!("{:?} {} {} {} {}", self.algorithm, key, start, end, first_only);
+        let (mut next, mut matches) = match fs::read_to_string(checkpoint) {
+            Ok(text) => self.read_checkpoint(&text, &search)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (start, Vec::new()),
+            Err(e) => return Err(e),
+        };
+        next = next.max(start);
+
+        while next < end && (!first_only || matches.is_empty()) {
+            // search() doesn't report the nonce it starts from.
+            if next > start && (next - start).is_multiple_of(PROGRESS_EVERY) {
+                progress(next, &matches);
+            }
+
+            let segment_end = next.saturating_add(CHECKPOINT_EVERY).min(end);
+            let found = self.search(predicate, next, segment_end, first_only, |n, found| {
+                let so_far: Vec<u32> = matches.iter().chain(found).cloned().collect();
+                progress(n, &so_far);
+            });
+            matches.extend(found);
+            next = segment_end;
+            self.write_checkpoint(checkpoint, &search, next, &matches)?;
         }
+
+        return Ok(matches);
+    }
+
+    // The prefix may hold any characters, even spaces or newlines, so it is
+    // matched against the start of the text rather than split out.
+    fn read_checkpoint(&self, text: &str, search: &str) -> io::Result<(u32, Vec<u32>)> {
+        let bad = |what: &str| io::Error::new(io::ErrorKind::InvalidData, format!("Checkpoint {}", what));
+
+        let rest = match text.strip_prefix(self.prefix.as_str()).and_then(|rest| rest.strip_prefix('\n')) {
+            Some(rest) => rest,
+            None => return Err(bad(&format!("is for prefix {}.", text.lines().next().unwrap_or("")))),
+        };
+        let mut lines = rest.lines();
+
+        let header = lines.next().unwrap_or("");
+        let (header_search, next) = match header.rsplit_once(' ') {
+            Some(fields) => fields,
+            None => return Err(bad("header is malformed.")),
+        };
+        if header_search != search {
+            return Err(bad(&format!("is for another search ({}).", header_search)));
+        }
+        let next = next.parse().map_err(|_| bad("has a bad next nonce."))?;
+
+        let mut matches = Vec::new();
+        for m in lines.next().unwrap_or("").split_whitespace() {
+            matches.push(m.parse().map_err(|_| bad("has a bad match."))?);
+        }
+
+        return Ok((next, matches));
+    }
+
+    // Written to a temporary file and renamed, so an interruption never
+    // leaves a half-written checkpoint behind.
+    fn write_checkpoint(&self, checkpoint: &Path, search: &str, next: u32, matches: &[u32]) -> io::Result<()> {
+        let matches: Vec<String> = matches.iter().map(|m| m.to_string()).collect();
+        let text = format!("{}\n{} {}\n{}\n", self.prefix, search, next, matches.join(" "));
+
+        let temp = checkpoint.with_extension("tmp");
+        fs::write(&temp, text)?;
+
+        return fs::rename(&temp, checkpoint);
+    }
+
+    fn default_threads() -> usize {
+        return thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
     }
 }
 
 impl super::Day for Mining {
     fn part1(&mut self) -> Result<i64, &str> {
-        return Ok(self.get_soln(5) as i64);
+        match self.get_soln_parallel(5, Mining::default_threads()) {
+            Some(n) => return Ok(n as i64),
+            None => return Err("No nonce gives five zeros."),
+        }
     }
 
     fn part2(&mut self) -> Result<i64, &str> {
-        return Ok(self.get_soln(6) as i64);
+        match self.get_soln_parallel(6, Mining::default_threads()) {
+            Some(n) => return Ok(n as i64),
+            None => return Err("No nonce gives six zeros."),
+        }
     }
 }
 
@@ -55,10 +371,220 @@ mod tests {
 
         for (s, zeros, n) in examples {
             let day = Mining::new(s);
-            assert_eq!(day.get_soln(zeros), n);
+            assert_eq!(day.get_soln(zeros), Some(n));
+        }
+    }
+
+    #[test]
+    fn test_parallel() {
+        let examples: Vec<(&str, usize, u32)> = vec![
+            ("abcdef", 5, 609043),
+            ("pqrstuv", 5, 1048970),
+            ("abcdef", 3, 3337),
+        ];
+
+        for (s, zeros, n) in examples {
+            let day = Mining::new(s);
+            for threads in [1, 3, 8] {
+                assert_eq!(day.get_soln_parallel(zeros, threads), Some(n));
+            }
         }
     }
 
+    #[test]
+    fn test_parallel_no_match() {
+        let day = Mining::new("abcdef");
+        let end = u32::MAX as u64 + 1;
+
+        // The search stops at the end of the nonce space instead of wrapping.
+        for threads in [1, 3] {
+            assert_eq!(day.first_parallel_in(end - 25000, end, &hex_prefix("0g"), threads), None);
+            assert_eq!(day.first_parallel_in(0, 5000, &zero_bits(12), threads), Some(3337));
+            assert_eq!(day.first_parallel_in(0, 3337, &zero_bits(12), threads), None);
+        }
+    }
+
+    #[test]
+    fn test_nonce_digits() {
+        let mut buf = [0u8; 10];
+        assert_eq!(Mining::nonce_digits(0, &mut buf), b"0");
+        assert_eq!(Mining::nonce_digits(609043, &mut buf), b"609043");
+        assert_eq!(Mining::nonce_digits(u32::MAX, &mut buf), b"4294967295");
+    }
+
+    #[test]
+    fn test_leading_zero_bits() {
+        assert_eq!(Mining::leading_zero_bits(&[0xff, 0]), 0);
+        assert_eq!(Mining::leading_zero_bits(&[0x00, 0x0f]), 12);
+        assert_eq!(Mining::leading_zero_bits(&[0x00, 0x01, 0]), 15);
+        assert_eq!(Mining::leading_zero_bits(&[0, 0]), 16);
+    }
+
+    #[test]
+    fn test_bits() {
+        let day = Mining::new("abcdef");
+
+        // Whole hex digits
+        assert_eq!(day.get_soln_bits(12), Some(3337));
+
+        // Difficulty between whole hex digits
+        assert_eq!(day.get_soln_bits(13), Some(11104));
+        assert_eq!(day.get_soln_parallel_bits(13, 4), Some(11104));
+    }
+
+    #[test]
+    fn test_search() {
+        let day = Mining::new("abcdef");
+
+        assert_eq!(day.find_first(&zero_bits(12), 0, 1000000), Some(3337));
+        assert_eq!(day.find_first(&zero_bits(12), 3338, 4000), None);
+        assert_eq!(day.find_all(&zero_bits(12), 0, 12000), vec![3337, 5568, 11104]);
+
+        let mut reports = Vec::new();
+        day.search(&zero_bits(12), 0, 250000, true, |n, matches| reports.push((n, matches.len())));
+        assert_eq!(reports, vec![]);
+        day.search(&zero_bits(12), 0, 250000, false, |n, _| reports.push((n, 0)));
+        assert_eq!(reports, vec![(100000, 0), (200000, 0)]);
+    }
+
+    #[test]
+    fn test_search_resumable() {
+        let day = Mining::new("abcdef");
+        let checkpoint = std::env::temp_dir().join(format!("mining_{}.ckpt", std::process::id()));
+        let _ = fs::remove_file(&checkpoint);
+
+        let bits = zero_bits(12);
+        let resume = |day: &Mining, key: &str, start: u32, end: u32, first_only: bool| {
+            day.search_resumable(&bits, key, start, end, first_only, &checkpoint, |_, _| {})
+        };
+
+        // Pretend an earlier run got as far as 5000.
+        fs::write(&checkpoint, "abcdef\nMD5 zero_bits(12) 0 12000 false 5000\n3337\n").unwrap();
+        let matches = resume(&day, "zero_bits(12)", 0, 12000, false).unwrap();
+        assert_eq!(matches, vec![3337, 5568, 11104]);
+        assert_eq!(fs::read_to_string(&checkpoint).unwrap(),
+                   "abcdef\nMD5 zero_bits(12) 0 12000 false 12000\n3337 5568 11104\n");
+
+        // Running again finds the search already complete.
+        let matches = resume(&day, "zero_bits(12)", 0, 12000, false).unwrap();
+        assert_eq!(matches, vec![3337, 5568, 11104]);
+
+        // A checkpoint for another search is refused.
+        assert!(resume(&Mining::new("pqrstuv"), "zero_bits(12)", 0, 12000, false).is_err());
+        assert!(resume(&Mining::new("abcde"), "zero_bits(12)", 0, 12000, false).is_err());
+        assert!(resume(&Mining::new("abcdef\nzero_bits(12)"), "zero_bits(12)", 0, 12000, false).is_err());
+
+        // So is one for another predicate or range, or for a first-only
+        // search.
+        for (key, start, end, first_only) in [("zero_bits(13)", 0, 12000, false), ("zero_bits(12)", 0, 20000, false),
+                                              ("zero_bits(12)", 1, 12000, false), ("zero_bits(12)", 0, 12000, true)] {
+            let error = resume(&day, key, start, end, first_only).unwrap_err();
+            assert_eq!(error.to_string(), "Checkpoint is for another search (MD5 zero_bits(12) 0 12000 false).");
+        }
+        assert_eq!(resume(&day, "zero\nbits", 0, 12000, false).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+
+        // And so is one made with another hash algorithm.
+        for algorithm in [Algorithm::SHA1, Algorithm::SHA256] {
+            let error = resume(&Mining::with_algorithm("abcdef", algorithm), "zero_bits(12)", 0, 12000, false)
+                .unwrap_err();
+            assert_eq!(error.to_string(), "Checkpoint is for another search (MD5 zero_bits(12) 0 12000 false).");
+        }
+
+        // Progress carries on from the checkpoint, across the segments
+        // between checkpoints, counting the matches from before.
+        fs::write(&checkpoint, "abcdef\nMD5 zero_bits(12) 0 1200000 false 900000\n3337\n").unwrap();
+        let mut reports = Vec::new();
+        let matches = day.search_resumable(&bits, "zero_bits(12)", 0, 1200000, false, &checkpoint,
+                                           |n, so_far| reports.push((n, so_far.to_vec()))).unwrap();
+        let expected: Vec<(u32, Vec<u32>)> = [900000, 1000000, 1100000].iter()
+            .map(|n| (*n, matches.iter().cloned().filter(|m| m < n).collect()))
+            .collect();
+        assert_eq!(reports, expected);
+        assert!(reports[2].1.len() > reports[0].1.len());
+        fs::remove_file(&checkpoint).unwrap();
+
+        // Prefixes with spaces and newlines can be resumed too.
+        for prefix in ["a b", "a\nb ", " "] {
+            let day = Mining::new(prefix);
+            let bits = zero_bits(8);
+            let matches = day.search_resumable(&bits, "8 bits", 0, 2000, false, &checkpoint, |_, _| {}).unwrap();
+            assert_eq!(day.search_resumable(&bits, "8 bits", 0, 2000, false, &checkpoint, |_, _| {}).unwrap(),
+                       matches);
+            assert_eq!(matches, day.find_all(&bits, 0, 2000));
+            assert!(!matches.is_empty());
+            fs::remove_file(&checkpoint).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_algorithms() {
+        let examples: Vec<(Algorithm, u32)> = vec![
+            (Algorithm::MD5, 3337),
+            (Algorithm::SHA1, 3693),
+            (Algorithm::SHA256, 7099),
+        ];
+
+        for (algorithm, n) in examples {
+            let day = Mining::with_algorithm("abcdef", algorithm);
+            assert_eq!(day.get_soln(3), Some(n));
+            assert_eq!(day.find_first_parallel(hex_prefix("000"), 4), Some(n));
+        }
+    }
+
+    #[test]
+    fn test_predicates() {
+        let digest = [0x0a, 0xbc, 0xde];
+        assert!(hex_prefix("0abcd")(&digest));
+        assert!(hex_prefix("0ABC")(&digest));
+        assert!(!hex_prefix("0abd")(&digest));
+        assert!(!hex_prefix("0abcdef0")(&digest));
+        assert!(!hex_prefix("0g")(&digest));
+
+        assert!(below_target(vec![0x0a, 0xbc, 0xdf])(&digest));
+        assert!(!below_target(vec![0x0a, 0xbc, 0xde])(&digest));
+        assert!(zero_bits(4)(&digest));
+        assert!(!zero_bits(5)(&digest));
+
+        let day = Mining::new("abcdef");
+        assert_eq!(day.find_first(&hex_prefix("abc"), 0, 100000), Some(6995));
+
+        let mut target = vec![0xff; 32];
+        target[0] = 0x00;
+        target[1] = 0x08;
+        let day = Mining::with_algorithm("abcdef", Algorithm::SHA256);
+        assert_eq!(day.find_first(&below_target(target), 0, 100000), Some(11214));
+    }
+
+    // The original check, formatting the key and the digest for every nonce.
+    fn is_soln_formatted(prefix: &str, n: u32, target_prefix: &str) -> bool {
+        let s = format!("{}{}", prefix, n.to_string());
+        let digest = md5::compute(s);
+
+        return format!("{:x}", digest).starts_with(target_prefix);
+    }
+
+    // cargo test --release bench_is_soln -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_is_soln() {
+        use std::time::Instant;
+
+        let day = Mining::new("iwrupvqb");
+        let nonces = 2000000;
+
+        let start = Instant::now();
+        let formatted = (0..nonces).filter(|n| is_soln_formatted("iwrupvqb", *n, "0000")).count();
+        let formatted_time = start.elapsed();
+
+        let start = Instant::now();
+        let direct = (0..nonces).filter(|n| day.is_soln(*n, 16)).count();
+        let direct_time = start.elapsed();
+
+        assert_eq!(formatted, direct);
+        println!("formatted: {:?}, direct: {:?}, speedup {:.2}x", formatted_time, direct_time,
+                 formatted_time.as_secs_f64() / direct_time.as_secs_f64());
+    }
+
     #[test]
     fn test_part1() {
         let mut day = Mining::new("iwrupvqb");
@@ -1,100 +1,444 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, Write};
+use serde_json::{json, Value};
 
 pub struct NaughtyNice {
     strings: Vec<String>,
 }
 
-impl NaughtyNice {
-    pub fn load(filename: &str) -> NaughtyNice {
-        let mut db = Vec::new();
+// A test a string passes or fails.  Rules can be built directly or parsed
+// from text such as
+//     all(min_count(aeiou, 3), run(2), not(contains(ab, cd, pq, xy)))
+#[allow(dead_code)]
+#[derive(Debug, PartialEq)]
+pub enum Rule {
+    // min_count(chars, n): at least n letters drawn from chars.
+    MinCount(String, usize),
+    // run(n): some letter appears n times in a row.
+    Run(usize),
+    // contains(s1, s2, ...): at least one of the substrings appears.
+    Contains(Vec<String>),
+    // repeated_pair: some pair of letters appears twice without overlapping.
+    RepeatedPair,
+    // gap(n): some letter repeats with exactly n letters between.
+    Gap(usize),
+    // all(r1, r2, ...), any(r1, r2, ...), not(r)
+    All(Vec<Rule>),
+    Any(Vec<Rule>),
+    Not(Box<Rule>),
+}
 
-        let file = File::open(filename).unwrap();
-        let reader = BufReader::new(file);
+// Why a string passed or failed a rule.  positions are character indices
+// backing up the evidence, e.g. where the vowels or the repeated pair are.
+#[derive(Debug, PartialEq)]
+pub struct Explanation {
+    pub rule: String,
+    pub passed: bool,
+    pub evidence: String,
+    pub positions: Vec<usize>,
+    pub children: Vec<Explanation>,
+}
 
-        for line in reader.lines() {
-            db.push(line.unwrap().trim().to_string());
+#[allow(dead_code)]
+impl Explanation {
+    pub fn to_json(&self) -> Value {
+        return json!({
+            "rule": self.rule,
+            "passed": self.passed,
+            "evidence": self.evidence,
+            "positions": self.positions,
+            "children": self.children.iter().map(|c| c.to_json()).collect::<Vec<Value>>(),
+        });
+    }
+
+    // One line per rule, children indented under their parent.
+    fn write_rows<W: Write>(&self, out: &mut W, depth: usize) -> io::Result<()> {
+        let result = if self.passed { "pass" } else { "FAIL" };
+        writeln!(out, "  {}  {:<width$}  {}", result, "  ".repeat(depth) + &self.rule, self.evidence,
+                 width = 50)?;
+        for child in &self.children {
+            child.write_rows(out, depth + 1)?;
         }
 
-        NaughtyNice {strings: db}
+        return Ok(());
     }
+}
 
-    fn is_nice(s: &str) -> bool {
-        let mut vowels = 0;
-        let mut pairs = 0;
-        let mut toxic = false;
+// Rules print in the same syntax Rule::parse() reads.
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let list = |rules: &Vec<Rule>| rules.iter().map(|r| r.to_string()).collect::<Vec<String>>().join(", ");
+        match self {
+            Rule::MinCount(chars, n) => write!(f, "min_count({}, {})", chars, n),
+            Rule::Run(n) => write!(f, "run({})", n),
+            Rule::Contains(subs) => write!(f, "contains({})", subs.join(", ")),
+            Rule::RepeatedPair => write!(f, "repeated_pair"),
+            Rule::Gap(n) => write!(f, "gap({})", n),
+            Rule::All(rules) => write!(f, "all({})", list(rules)),
+            Rule::Any(rules) => write!(f, "any({})", list(rules)),
+            Rule::Not(rule) => write!(f, "not({})", rule),
+        }
+    }
+}
 
-        let mut last_c = '~';
-        for c in s.chars() {
-            if "aeiou".contains(c) {
-                vowels += 1;
+// Parsed but not yet interpreted rule text: a name and its arguments.
+struct Expr {
+    name: String,
+    args: Vec<Expr>,
+}
+
+impl Rule {
+    // The rules from part 1 of the puzzle.
+    pub fn part1() -> Rule {
+        return Rule::All(vec![
+            Rule::MinCount("aeiou".to_string(), 3),
+            Rule::Run(2),
+            Rule::Not(Box::new(Rule::Contains(
+                vec!["ab".to_string(), "cd".to_string(), "pq".to_string(), "xy".to_string()]))),
+        ]);
+    }
+
+    // The rules from part 2 of the puzzle.
+    pub fn part2() -> Rule {
+        return Rule::All(vec![Rule::RepeatedPair, Rule::Gap(1)]);
+    }
+
+    pub fn matches(&self, s: &str) -> bool {
+        match self {
+            Rule::MinCount(chars, n) => s.chars().filter(|c| chars.contains(*c)).count() >= *n,
+            Rule::Run(n) => {
+                let mut run = 0;
+                let mut last_c = None;
+                for c in s.chars() {
+                    run = if Some(c) == last_c { run + 1 } else { 1 };
+                    last_c = Some(c);
+                    if run >= *n {
+                        return true;
+                    }
+                }
+                *n == 0
             }
-            if c == last_c {
-                pairs += 1
+            Rule::Contains(subs) => subs.iter().any(|sub| s.contains(sub.as_str())),
+            Rule::RepeatedPair => {
+                // Remember where each pair first starts; a later copy at
+                // least two letters on doesn't overlap it.
+                let mut first_seen: HashMap<(char, char), usize> = HashMap::new();
+                let mut last_c = None;
+                for (n, c) in s.chars().enumerate() {
+                    if let Some(prev) = last_c {
+                        let start = *first_seen.entry((prev, c)).or_insert(n - 1);
+                        if n > start + 2 {
+                            return true;
+                        }
+                    }
+                    last_c = Some(c);
+                }
+                false
             }
-            last_c = c;
+            Rule::Gap(gap) => {
+                // The last gap+1 letters, oldest first; never more than the
+                // string holds, however big the gap.
+                let mut window: VecDeque<char> = VecDeque::with_capacity((*gap).min(s.len()));
+                for c in s.chars() {
+                    if window.len() > *gap && window.pop_front() == Some(c) {
+                        return true;
+                    }
+                    window.push_back(c);
+                }
+                false
+            }
+            Rule::All(rules) => rules.iter().all(|r| r.matches(s)),
+            Rule::Any(rules) => rules.iter().any(|r| r.matches(s)),
+            Rule::Not(rule) => !rule.matches(s),
         }
+    }
 
-        let bads = ["ab", "cd", "pq", "xy"];
-        for bad_str in bads {
-            if s.contains(bad_str) {
-                toxic = true
+    // Like matches(), but recording the evidence for each rule's result.
+    #[allow(dead_code)]
+    pub fn explain(&self, s: &str) -> Explanation {
+        let s_chars: Vec<char> = s.chars().collect();
+        let text = |from: usize, len: usize| s_chars[from..from+len].iter().collect::<String>();
+        let leaf = |passed: bool, evidence: String, positions: Vec<usize>| Explanation {
+            rule: self.to_string(),
+            passed: passed,
+            evidence: evidence,
+            positions: positions,
+            children: Vec::new(),
+        };
+
+        match self {
+            Rule::MinCount(chars, n) => {
+                let positions: Vec<usize> = (0..s_chars.len()).filter(|i| chars.contains(s_chars[*i])).collect();
+                let at: Vec<String> = positions.iter().map(|i| i.to_string()).collect();
+                leaf(positions.len() >= *n,
+                     format!("{} of {} at [{}]", positions.len(), chars, at.join(", ")),
+                     positions)
+            }
+            Rule::Run(n) => {
+                let found = (0..(s_chars.len()+1).saturating_sub(*n))
+                    .find(|i| s_chars[*i..*i+*n].iter().all(|c| *c == s_chars[*i]));
+                match found {
+                    Some(i) => leaf(true, format!("'{}' at {}", text(i, *n), i), vec![i]),
+                    None => leaf(*n == 0, format!("no letter {} times in a row", n), vec![]),
+                }
+            }
+            Rule::Contains(subs) => {
+                let sub_chars: Vec<Vec<char>> = subs.iter().map(|sub| sub.chars().collect()).collect();
+                let found = (0..s_chars.len())
+                    .flat_map(|i| subs.iter().zip(&sub_chars).map(move |sub| (i, sub)))
+                    .find(|(i, (_, chars))| s_chars[*i..].starts_with(chars))
+                    .map(|(i, (sub, _))| (i, sub));
+                match found {
+                    Some((i, sub)) => leaf(true, format!("'{}' at {}", sub, i), vec![i]),
+                    None => leaf(false, format!("none of {}", subs.join(", ")), vec![]),
+                }
+            }
+            Rule::RepeatedPair => {
+                let found = (0..s_chars.len().saturating_sub(1))
+                    .flat_map(|i| (i+2..s_chars.len().saturating_sub(1)).map(move |j| (i, j)))
+                    .find(|(i, j)| s_chars[*i..*i+2] == s_chars[*j..*j+2]);
+                match found {
+                    Some((i, j)) => leaf(true, format!("'{}' at {} and {}", text(i, 2), i, j), vec![i, j]),
+                    None => leaf(false, "no pair appears twice".to_string(), vec![]),
+                }
+            }
+            Rule::Gap(gap) => {
+                let apart = gap.saturating_add(1);
+                let found = (0..s_chars.len().saturating_sub(apart)).find(|i| s_chars[*i] == s_chars[*i+apart]);
+                match found {
+                    Some(i) => leaf(true, format!("'{}' at {}", text(i, apart + 1), i), vec![i, i+apart]),
+                    None => leaf(false, format!("no letter repeats {} apart", apart), vec![]),
+                }
+            }
+            Rule::All(rules) | Rule::Any(rules) => {
+                let children: Vec<Explanation> = rules.iter().map(|r| r.explain(s)).collect();
+                let passing = children.iter().filter(|c| c.passed).count();
+                let passed = match self {
+                    Rule::All(_) => passing == children.len(),
+                    _ => passing > 0,
+                };
+                Explanation {
+                    rule: self.to_string(),
+                    passed: passed,
+                    evidence: format!("{} of {} passed", passing, children.len()),
+                    positions: vec![],
+                    children: children,
+                }
+            }
+            Rule::Not(rule) => {
+                let child = rule.explain(s);
+                Explanation {
+                    rule: self.to_string(),
+                    passed: !child.passed,
+                    evidence: String::new(),
+                    positions: vec![],
+                    children: vec![child],
+                }
             }
         }
-
-        (vowels >= 3) & (pairs >= 1) & !toxic
     }
 
+    #[allow(dead_code)]
+    pub fn parse(text: &str) -> Result<Rule, String> {
+        let tokens = Rule::tokenize(text)?;
+        let mut pos = 0;
+        let expr = Rule::parse_expr(&tokens, &mut pos)?;
+        if pos != tokens.len() {
+            return Err(format!("Unexpected '{}' after rule.", tokens[pos]));
+        }
 
-    fn is_nice2(s: &str) -> bool {
-        let mut criteria1 = false;
-        let mut criteria2 = false;
+        return Rule::from_expr(&expr);
+    }
 
-        let s_chars:Vec<char> = s.chars().collect();
+    // Split into names and the punctuation ( ) ,
+    fn tokenize(text: &str) -> Result<Vec<String>, String> {
+        let mut tokens = Vec::new();
+        let mut word = String::new();
+        for c in text.chars() {
+            if c.is_alphanumeric() || c == '_' {
+                word.push(c);
+                continue;
+            }
+            if !word.is_empty() {
+                tokens.push(word);
+                word = String::new();
+            }
+            match c {
+                '(' | ')' | ',' => tokens.push(c.to_string()),
+                _ if c.is_whitespace() => (),
+                _ => return Err(format!("Unexpected character '{}' in rule.", c)),
+            }
+        }
+        if !word.is_empty() {
+            tokens.push(word);
+        }
 
-        // Check for criteria 1
-        'outer: for n in 0..s_chars.len()-3 {
-            for m in n+2..s_chars.len()-1 {
-                if s_chars[n] == s_chars[m] && s_chars[n+1] == s_chars[m+1] {
-                    criteria1 = true;
-                    break 'outer;
+        return Ok(tokens);
+    }
+
+    fn parse_expr(tokens: &[String], pos: &mut usize) -> Result<Expr, String> {
+        let name = match tokens.get(*pos) {
+            Some(t) if !"(),".contains(t.as_str()) => t.clone(),
+            Some(t) => return Err(format!("Expected a name, found '{}'.", t)),
+            None => return Err("Rule ends early.".to_string()),
+        };
+        *pos += 1;
+
+        let mut args = Vec::new();
+        if tokens.get(*pos).map(|t| t.as_str()) == Some("(") {
+            *pos += 1;
+            loop {
+                args.push(Rule::parse_expr(tokens, pos)?);
+                match tokens.get(*pos).map(|t| t.as_str()) {
+                    Some(",") => *pos += 1,
+                    Some(")") => {
+                        *pos += 1;
+                        break;
+                    }
+                    _ => return Err(format!("Expected ',' or ')' in arguments to {}.", name)),
                 }
             }
         }
 
-        // Check for criteria 2
-        for n in 0..s_chars.len()-2 {
-            if s_chars[n] == s_chars[n+2] {
-                criteria2 = true;
-                break;
+        return Ok(Expr { name: name, args: args });
+    }
+
+    fn from_expr(expr: &Expr) -> Result<Rule, String> {
+        // Arguments that are plain words or numbers rather than rules
+        let words = || -> Result<Vec<String>, String> {
+            expr.args.iter().map(|a| {
+                if a.args.is_empty() { Ok(a.name.clone()) }
+                else { Err(format!("Expected a word in arguments to {}, found {}(...).", expr.name, a.name)) }
+            }).collect()
+        };
+        let number = |word: &str| -> Result<usize, String> {
+            word.parse().map_err(|_| format!("Expected a number in arguments to {}, found {}.", expr.name, word))
+        };
+        let arity = |n: usize| -> Result<(), String> {
+            if expr.args.len() == n { Ok(()) }
+            else { Err(format!("{} takes {} arguments, found {}.", expr.name, n, expr.args.len())) }
+        };
+
+        match expr.name.as_str() {
+            "min_count" => {
+                arity(2)?;
+                let w = words()?;
+                Ok(Rule::MinCount(w[0].clone(), number(&w[1])?))
+            }
+            "run" => {
+                arity(1)?;
+                Ok(Rule::Run(number(&words()?[0])?))
+            }
+            "contains" => Ok(Rule::Contains(words()?)),
+            "repeated_pair" => {
+                arity(0)?;
+                Ok(Rule::RepeatedPair)
+            }
+            "gap" => {
+                arity(1)?;
+                let gap = number(&words()?[0])?;
+                // Matching counts gap+2 letters from the first to the second.
+                match gap.checked_add(2) {
+                    Some(_) => Ok(Rule::Gap(gap)),
+                    None => Err(format!("gap({}) is too large.", gap)),
+                }
+            }
+            "all" | "any" => {
+                let rules = expr.args.iter().map(Rule::from_expr).collect::<Result<Vec<Rule>, String>>()?;
+                Ok(if expr.name == "all" { Rule::All(rules) } else { Rule::Any(rules) })
             }
+            "not" => {
+                arity(1)?;
+                Ok(Rule::Not(Box::new(Rule::from_expr(&expr.args[0])?)))
+            }
+            _ => Err(format!("Unknown rule '{}'.", expr.name)),
         }
+    }
+}
+
+impl NaughtyNice {
+    pub fn load(filename: &str) -> NaughtyNice {
+        let mut db = Vec::new();
 
-        criteria1 & criteria2
+        let file = File::open(filename).unwrap();
+        let reader = BufReader::new(file);
+
+        for line in reader.lines() {
+            db.push(line.unwrap().trim().to_string());
+        }
+
+        NaughtyNice {strings: db}
     }
 
-    fn num_nice(&self) -> usize {
-        let mut count = 0;
+    #[cfg(test)]
+    fn is_nice(s: &str) -> bool {
+        return Rule::part1().matches(s);
+    }
 
-        for s in &self.strings {
-            if NaughtyNice::is_nice(s) {
+    #[cfg(test)]
+    fn is_nice2(s: &str) -> bool {
+        return Rule::part2().matches(s);
+    }
+
+    // Count lines from reader passing the rule, one line at a time, so word
+    // lists of any size can be classified without loading them.
+    #[allow(dead_code)]
+    pub fn count_matching<R: BufRead>(mut reader: R, rule: &Rule) -> io::Result<usize> {
+        let mut count = 0;
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 0 {
+            if rule.matches(line.trim()) {
                 count += 1;
             }
+            line.clear();
         }
 
-        count
+        return Ok(count);
     }
 
-    fn num_nice2(&self) -> usize {
-        let mut count = 0;
+    #[allow(dead_code)]
+    pub fn count_matching_file(filename: &str, rule: &Rule) -> io::Result<usize> {
+        let file = File::open(filename)?;
+
+        return NaughtyNice::count_matching(BufReader::new(file), rule);
+    }
+
+    // Number of strings passing the rule.
+    pub fn num_matching(&self, rule: &Rule) -> usize {
+        return self.strings.iter().filter(|s| rule.matches(s)).count();
+    }
 
+    // For every string, whether it passes the rule and why.
+    #[allow(dead_code)]
+    pub fn write_explanations<W: Write>(&self, rule: &Rule, mut out: W) -> io::Result<()> {
         for s in &self.strings {
-            if NaughtyNice::is_nice2(s) {
-                count += 1;
-            }
+            let explanation = rule.explain(s);
+            writeln!(out, "{}: {}", s, if explanation.passed { "nice" } else { "naughty" })?;
+            explanation.write_rows(&mut out, 0)?;
         }
 
-        count
+        return Ok(());
+    }
+
+    // The same as write_explanations(), as a JSON array.
+    #[allow(dead_code)]
+    pub fn explanations_json(&self, rule: &Rule) -> Value {
+        let explained: Vec<Value> = self.strings.iter().map(|s| {
+            let explanation = rule.explain(s);
+            json!({ "string": s, "nice": explanation.passed, "explanation": explanation.to_json() })
+        }).collect();
+
+        return Value::Array(explained);
+    }
+
+    fn num_nice(&self) -> usize {
+        return self.num_matching(&Rule::part1());
+    }
+
+    fn num_nice2(&self) -> usize {
+        return self.num_matching(&Rule::part2());
     }
 }
 
@@ -151,6 +495,153 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_rules() {
+        assert!(Rule::MinCount("aeiou".to_string(), 3).matches("xazegov"));
+        assert!(!Rule::MinCount("aeiou".to_string(), 3).matches("aexyz"));
+        assert!(Rule::Run(3).matches("abbbc"));
+        assert!(!Rule::Run(3).matches("abbcc"));
+        assert!(Rule::Contains(vec!["xy".to_string()]).matches("axyb"));
+        assert!(Rule::RepeatedPair.matches("aabcdefgaa"));
+        assert!(!Rule::RepeatedPair.matches("aaa"));
+        assert!(Rule::Gap(1).matches("abcdefeghi"));
+        assert!(Rule::Gap(2).matches("abca"));
+        assert!(!Rule::Gap(2).matches("abcb"));
+        assert!(Rule::Any(vec![Rule::Run(3), Rule::Gap(1)]).matches("aba"));
+        // A gap far longer than the string can't match, or exhaust memory
+        assert!(!Rule::Gap(100000000000000).matches("abcabc"));
+        assert!(!Rule::Gap(usize::MAX).matches("abcabc"));
+        assert!(!Rule::Gap(usize::MAX).explain("abcabc").passed);
+        assert!(Rule::Not(Box::new(Rule::Run(2))).matches("abc"));
+    }
+
+    #[test]
+    fn test_parse() {
+        let rule = Rule::parse("all(min_count(aeiou, 3), run(2), not(contains(ab, cd, pq, xy)))");
+        assert_eq!(rule, Ok(Rule::part1()));
+
+        let rule = Rule::parse(" all( repeated_pair , gap(1) ) ");
+        assert_eq!(rule, Ok(Rule::part2()));
+
+        // A new variant without any new code
+        let rule = Rule::parse("any(run(3), not(min_count(aeiou, 1)))").unwrap();
+        let nn = NaughtyNice { strings: vec!["xyz".to_string(), "abbbc".to_string(), "abc".to_string()] };
+        assert_eq!(nn.num_matching(&rule), 2);
+
+        assert!(Rule::parse("run(two)").is_err());
+        assert!(Rule::parse("run(2, 3)").is_err());
+        assert!(Rule::parse("all(run(2)").is_err());
+        assert!(Rule::parse("sometimes(2)").is_err());
+        assert!(Rule::parse("contains(run(2))").is_err());
+        assert!(Rule::parse("run(2) gap(1)").is_err());
+        assert_eq!(Rule::parse(&format!("gap({})", usize::MAX)), Err(format!("gap({}) is too large.", usize::MAX)));
+        assert!(Rule::parse("gap(100000000000000)").is_ok());
+    }
+
+    // The original quadratic check for a repeated pair.
+    fn repeated_pair_slow(s: &str) -> bool {
+        let s_chars: Vec<char> = s.chars().collect();
+        for n in 0..s_chars.len().saturating_sub(3) {
+            for m in n+2..s_chars.len()-1 {
+                if s_chars[n] == s_chars[m] && s_chars[n+1] == s_chars[m+1] {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    #[test]
+    fn test_single_pass() {
+        // Every string of up to 6 letters over a small alphabet
+        let mut strings = vec![String::new()];
+        let mut level = vec![String::new()];
+        for _ in 0..6 {
+            level = level.iter()
+                .flat_map(|s| ['a', 'b', 'c'].iter().map(move |c| format!("{}{}", s, c)))
+                .collect();
+            strings.extend(level.iter().cloned());
+        }
+
+        for s in &strings {
+            assert_eq!(Rule::RepeatedPair.matches(s), repeated_pair_slow(s), "{}", s);
+            for gap in 0..3 {
+                let chars: Vec<char> = s.chars().collect();
+                let expected = (0..chars.len().saturating_sub(gap + 1)).any(|n| chars[n] == chars[n+gap+1]);
+                assert_eq!(Rule::Gap(gap).matches(s), expected, "{} gap {}", s, gap);
+            }
+        }
+    }
+
+    #[test]
+    fn test_count_matching() {
+        let input = "ugknbfddgicrmopn\naaa\njchzalrnumimnmhp\nhaegwjzuvuyypxyu\ndvszwmarrgswjxmb\n";
+        assert_eq!(NaughtyNice::count_matching(input.as_bytes(), &Rule::part1()).unwrap(), 2);
+
+        let input = "qjhvhtzxzqqjkmpb\nxxyxx\nuurcxstgmygtbstg\nieodomkazucvgmuy";
+        assert_eq!(NaughtyNice::count_matching(input.as_bytes(), &Rule::part2()).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_display() {
+        let text = "all(min_count(aeiou, 3), run(2), not(contains(ab, cd, pq, xy)))";
+        assert_eq!(Rule::part1().to_string(), text);
+        assert_eq!(Rule::part2().to_string(), "all(repeated_pair, gap(1))");
+    }
+
+    #[test]
+    fn test_explain() {
+        // "naughty because it contains the string xy"
+        let explanation = Rule::part1().explain("haegwjzuvuyypxyu");
+        assert!(!explanation.passed);
+        let forbidden = &explanation.children[2].children[0];
+        assert_eq!(forbidden.evidence, "'xy' at 13");
+        assert_eq!(forbidden.positions, vec![13]);
+
+        // "naughty because it has no double letter"
+        let explanation = Rule::part1().explain("jchzalrnumimnmhp");
+        assert_eq!(explanation.children[0].positions, vec![4, 8, 10]);
+        assert!(!explanation.children[1].passed);
+
+        // "naughty because it only contains one vowel"
+        let explanation = Rule::part1().explain("dvszwmarrgswjxmb");
+        assert_eq!(explanation.children[0].evidence, "1 of aeiou at [6]");
+        assert_eq!(explanation.children[1].evidence, "'rr' at 7");
+
+        // "nice because is has a pair that appears twice (qj) and a letter
+        // that repeats with exactly one letter between them (zxz)"
+        let explanation = Rule::part2().explain("qjhvhtzxzqqjkmpb");
+        assert!(explanation.passed);
+        assert_eq!(explanation.children[0].positions, vec![0, 10]);
+        assert_eq!(explanation.children[1].evidence, "'hvh' at 2");
+
+        // The explanation agrees with matches()
+        let examples = ["ugknbfddgicrmopn", "aaa", "xxyxx", "uurcxstgmygtbstg", "ieodomkazucvgmuy"];
+        for s in examples {
+            assert_eq!(Rule::part1().explain(s).passed, Rule::part1().matches(s));
+            assert_eq!(Rule::part2().explain(s).passed, Rule::part2().matches(s));
+        }
+    }
+
+    #[test]
+    fn test_explanations_output() {
+        let nn = NaughtyNice { strings: vec!["aaa".to_string()] };
+
+        let mut out: Vec<u8> = Vec::new();
+        nn.write_explanations(&Rule::part2(), &mut out).unwrap();
+        let table = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines[0], "aaa: naughty");
+        assert!(lines[1].starts_with("  FAIL  all(repeated_pair, gap(1))"));
+        assert!(lines[2].starts_with("  FAIL    repeated_pair"));
+        assert!(lines[3].ends_with("'aaa' at 0"));
+
+        let json = nn.explanations_json(&Rule::part2());
+        assert_eq!(json[0]["string"], "aaa");
+        assert_eq!(json[0]["nice"], false);
+        assert_eq!(json[0]["explanation"]["children"][1]["positions"], json!([0, 2]));
+    }
+
     #[test]
     fn test_part1() {
         let mut day = NaughtyNice::load("data/day5_input.txt");
@@ -1,10 +1,18 @@
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, Read};
 
 pub struct NotQuiteLisp {
     s: String,
 }
 
+// Result of running instructions straight from a reader, see scan_reader().
+#[derive(Debug, PartialEq)]
+pub struct FloorSummary {
+    pub floor: i32,
+    pub basement_pos: Option<usize>,
+}
+
 impl NotQuiteLisp {
     pub fn load(filename: &str) -> NotQuiteLisp {
         let file = File::open(filename).unwrap();
@@ -22,34 +30,115 @@ impl NotQuiteLisp {
     }
 
     pub fn floor(&self) -> i32 {
-        let mut n = 0;
-        for c in self.s.chars() {
+        return self.floors().last().unwrap_or(0);
+    }
+
+    // Floor Santa is on after each instruction.  Item n is the floor after
+    // position n+1; characters other than parens leave the floor unchanged.
+    pub fn floors(&self) -> impl Iterator<Item = i32> + '_ {
+        self.s.chars().scan(0, |n, c| {
             match c {
-                '(' => n += 1,
-                ')' => n -= 1,
+                '(' => *n += 1,
+                ')' => *n -= 1,
                 _ => (),
             }
+            Some(*n)
+        })
+    }
+
+    // First position at which Santa is on the given floor.  Position 0 is the
+    // start, before any instruction, so floor 0 is always reached at 0.
+    pub fn first_reaching(&self, floor: i32) -> Option<usize> {
+        if floor == 0 {
+            return Some(0);
+        }
+
+        return self.floors().position(|n| n == floor).map(|pos| pos + 1);
+    }
+
+    // Lowest floor reached and the first position it was reached at.
+    #[allow(dead_code)]
+    pub fn min_floor(&self) -> (i32, usize) {
+        let mut lowest = (0, 0);
+        for (pos, n) in self.floors().enumerate() {
+            if n < lowest.0 {
+                lowest = (n, pos + 1);
+            }
         }
 
-        return n;
+        return lowest;
     }
 
-    pub fn basement_pos(&self) -> i32 {
+    // Highest floor reached and the first position it was reached at.
+    #[allow(dead_code)]
+    pub fn max_floor(&self) -> (i32, usize) {
+        let mut highest = (0, 0);
+        for (pos, n) in self.floors().enumerate() {
+            if n > highest.0 {
+                highest = (n, pos + 1);
+            }
+        }
+
+        return highest;
+    }
+
+    // Number of positions spent on each floor, counting the start on floor 0.
+    #[allow(dead_code)]
+    pub fn time_per_floor(&self) -> HashMap<i32, usize> {
+        let mut times: HashMap<i32, usize> = HashMap::new();
+        times.insert(0, 1);
+
+        for n in self.floors() {
+            *times.entry(n).or_insert(0) += 1;
+        }
+
+        return times;
+    }
+
+    // Position of the first instruction that takes Santa into the basement,
+    // or None if he never goes below floor 0.
+    pub fn basement_pos(&self) -> Option<usize> {
+        return self.first_reaching(-1);
+    }
+
+    // Follow instructions read from any reader without holding them in memory.
+    #[allow(dead_code)]
+    pub fn scan_reader<R: Read>(mut reader: R) -> io::Result<FloorSummary> {
+        let mut buf = [0u8; 8192];
         let mut n = 0;
         let mut position = 0;
-        for c in self.s.chars() {
-            position += 1;
-            match c {
-                '(' => n += 1,
-                ')' => n -= 1,
-                _ => (),
-            }
-            if n < 0 {
-                return position;
+        let mut basement_pos = None;
+
+        loop {
+            let len = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(len) => len,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+
+            for b in &buf[..len] {
+                position += 1;
+                match b {
+                    b'(' => n += 1,
+                    b')' => n -= 1,
+                    _ => (),
+                }
+                if n < 0 && basement_pos.is_none() {
+                    basement_pos = Some(position);
+                }
             }
         }
 
-        return n;
+        return Ok(FloorSummary { floor: n, basement_pos: basement_pos });
+    }
+
+    // Streaming counterpart to load(), for instruction files too big to read in.
+    #[allow(dead_code)]
+    pub fn scan_file(filename: &str) -> io::Result<FloorSummary> {
+        let file = File::open(filename)?;
+
+        return NotQuiteLisp::scan_reader(BufReader::new(file));
     }
 
 }
@@ -60,7 +149,10 @@ impl super::Day for NotQuiteLisp {
     }
 
     fn part2(&mut self) -> Result<i64, &str> {
-        return Ok(self.basement_pos() as i64);
+        match self.basement_pos() {
+            Some(pos) => return Ok(pos as i64),
+            None => return Err("Santa never entered the basement."),
+        }
     }
 }
 
@@ -91,9 +183,11 @@ mod tests {
 
     #[test]
     fn test_basement_pos() {
-        let examples: Vec<(&str, i32)> = vec![
-            (")", 1),
-            ("()())", 5),
+        let examples: Vec<(&str, Option<usize>)> = vec![
+            (")", Some(1)),
+            ("()())", Some(5)),
+            ("(()", None),
+            ("", None),
         ];
 
         for (s, n) in examples {
@@ -102,6 +196,59 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_scan_reader() {
+        let examples: Vec<&str> = vec![")", "()())", "(()", "))(((((", ""];
+
+        for s in examples {
+            let nql = NotQuiteLisp::from_str(s);
+            let summary = NotQuiteLisp::scan_reader(s.as_bytes()).unwrap();
+            assert_eq!(summary, FloorSummary { floor: nql.floor(), basement_pos: nql.basement_pos() });
+        }
+
+        // Longer than one read buffer
+        let long = "(".repeat(10000) + &")".repeat(10001);
+        let summary = NotQuiteLisp::scan_reader(long.as_bytes()).unwrap();
+        assert_eq!(summary, FloorSummary { floor: -1, basement_pos: Some(20001) });
+    }
+
+    #[test]
+    fn test_floors() {
+        let nql = NotQuiteLisp::from_str("(()))(");
+        let floors: Vec<i32> = nql.floors().collect();
+        assert_eq!(floors, vec![1, 2, 1, 0, -1, 0]);
+    }
+
+    #[test]
+    fn test_first_reaching() {
+        let nql = NotQuiteLisp::from_str("(()))(");
+        assert_eq!(nql.first_reaching(0), Some(0));
+        assert_eq!(nql.first_reaching(2), Some(2));
+        assert_eq!(nql.first_reaching(-1), Some(5));
+        assert_eq!(nql.first_reaching(3), None);
+    }
+
+    #[test]
+    fn test_min_max_floor() {
+        let nql = NotQuiteLisp::from_str("(()))()))");
+        assert_eq!(nql.max_floor(), (2, 2));
+        assert_eq!(nql.min_floor(), (-3, 9));
+
+        let nql = NotQuiteLisp::from_str("");
+        assert_eq!(nql.max_floor(), (0, 0));
+        assert_eq!(nql.min_floor(), (0, 0));
+    }
+
+    #[test]
+    fn test_time_per_floor() {
+        let nql = NotQuiteLisp::from_str("(()))(");
+        let times = nql.time_per_floor();
+        assert_eq!(times[&0], 3);
+        assert_eq!(times[&1], 2);
+        assert_eq!(times[&2], 1);
+        assert_eq!(times[&-1], 1);
+    }
+
     #[test]
     fn test_load() {}
 
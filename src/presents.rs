@@ -1,80 +1,472 @@
+use std::collections::BTreeMap;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, Write};
 use lazy_static::lazy_static;
 use regex::Regex;
 
+// Box dimensions in whole feet.
+pub type Dims = (u64, u64, u64);
+
 pub struct Presents {
-    dims: Vec<(u32, u32, u32)>,
+    dims: Vec<Dims>,
+}
+
+// Paper (square feet) and ribbon (feet) needed for one box of the order.
+#[derive(Debug, PartialEq)]
+pub struct BoxUsage {
+    pub index: usize,
+    pub dims: Dims,
+    pub volume: u64,
+    pub paper: u64,
+    pub ribbon: u64,
+}
+
+// Per-box breakdown of a whole order, built by Presents::report().
+pub struct OrderReport {
+    pub boxes: Vec<BoxUsage>,
+    pub total_paper: u64,
+    pub total_ribbon: u64,
+}
+
+#[allow(dead_code)]
+impl OrderReport {
+    pub fn most_paper(&self) -> Option<&BoxUsage> {
+        return self.boxes.iter().max_by_key(|b| b.paper);
+    }
+
+    pub fn least_paper(&self) -> Option<&BoxUsage> {
+        return self.boxes.iter().min_by_key(|b| b.paper);
+    }
+
+    pub fn most_ribbon(&self) -> Option<&BoxUsage> {
+        return self.boxes.iter().max_by_key(|b| b.ribbon);
+    }
+
+    pub fn least_ribbon(&self) -> Option<&BoxUsage> {
+        return self.boxes.iter().min_by_key(|b| b.ribbon);
+    }
+
+    // Number of boxes per volume bucket, keyed by the bucket's lowest volume.
+    pub fn volume_histogram(&self, bucket_size: u64) -> Result<BTreeMap<u64, usize>, String> {
+        if bucket_size == 0 {
+            return Err("Histogram buckets must hold at least one cubic foot.".to_string());
+        }
+
+        let mut histogram: BTreeMap<u64, usize> = BTreeMap::new();
+        for b in &self.boxes {
+            let bucket = b.volume / bucket_size * bucket_size;
+            *histogram.entry(bucket).or_insert(0) += 1;
+        }
+
+        return Ok(histogram);
+    }
+
+    // Whole rolls needed to cover total_paper, for rolls of the given width
+    // and length in feet.
+    pub fn rolls_needed(&self, roll_width: u64, roll_length: u64) -> Result<u64, String> {
+        let roll_area = roll_width.checked_mul(roll_length)
+            .ok_or(format!("A {}x{} roll overflows 64 bits.", roll_width, roll_length))?;
+        if roll_area == 0 {
+            return Err(format!("A {}x{} roll has no paper.", roll_width, roll_length));
+        }
+        let rolls = self.total_paper / roll_area;
+
+        return Ok(if self.total_paper % roll_area == 0 { rolls } else { rolls + 1 });
+    }
+
+    // One line per box, after a header line.
+    pub fn write_csv<W: Write>(&self, mut out: W) -> io::Result<()> {
+        writeln!(out, "index,length,width,height,volume,paper,ribbon")?;
+        for b in &self.boxes {
+            writeln!(out, "{},{},{},{},{},{},{}",
+                     b.index, b.dims.0, b.dims.1, b.dims.2, b.volume, b.paper, b.ribbon)?;
+        }
+
+        return Ok(());
+    }
+}
+
+// Paper (square feet) and ribbon (feet) needed for one box of a manifest.
+#[derive(Debug, PartialEq)]
+pub struct SpecUsage {
+    pub index: usize,
+    pub sides: Vec<f64>,
+    pub volume: f64,
+    pub paper: f64,
+    pub ribbon: f64,
+}
+
+// Per-box breakdown of a manifest of general boxes, built by
+// Presents::manifest_report().
+#[allow(dead_code)]
+pub struct ManifestReport {
+    pub boxes: Vec<SpecUsage>,
+    pub total_paper: f64,
+    pub total_ribbon: f64,
+}
+
+#[allow(dead_code)]
+impl ManifestReport {
+    // Whole rolls needed to cover total_paper, for rolls of the given width
+    // and length in feet.
+    pub fn rolls_needed(&self, roll_width: f64, roll_length: f64) -> Result<u64, String> {
+        let roll_area = roll_width * roll_length;
+        if !(roll_area > 0.0 && roll_area.is_finite()) {
+            return Err(format!("A {}x{} roll has no usable paper.", roll_width, roll_length));
+        }
+        let rolls = (self.total_paper / roll_area).ceil();
+        if rolls > u64::MAX as f64 {
+            return Err("Rolls needed overflow 64 bits.".to_string());
+        }
+
+        return Ok(rolls as u64);
+    }
+
+    // One line per box, after a header line, with the sides joined by 'x'.
+    pub fn write_csv<W: Write>(&self, mut out: W) -> io::Result<()> {
+        writeln!(out, "index,sides,volume,paper,ribbon")?;
+        for b in &self.boxes {
+            let sides: Vec<String> = b.sides.iter().map(|side| side.to_string()).collect();
+            writeln!(out, "{},{},{},{},{}", b.index, sides.join("x"), b.volume, b.paper, b.ribbon)?;
+        }
+
+        return Ok(());
+    }
+}
+
+// One wrap piece cut from the roll.  x runs across the roll's width and y
+// along its length; across/along are the piece's extent in those directions.
+#[derive(Debug, PartialEq)]
+pub struct Placement {
+    pub index: usize,
+    pub x: u64,
+    pub y: u64,
+    pub across: u64,
+    pub along: u64,
+}
+
+// Where every box's wrap piece is cut, built by Presents::cut_layout().
+pub struct CutLayout {
+    pub roll_width: u64,
+    pub roll_length: u64,
+    pub placements: Vec<Placement>,
+}
+
+#[allow(dead_code)]
+impl CutLayout {
+    // Fraction of the used roll area that ends up on a box.
+    pub fn utilization(&self) -> f64 {
+        let used: f64 = self.placements.iter()
+            .map(|p| p.across as f64 * p.along as f64)
+            .sum();
+        let total = self.roll_width as f64 * self.roll_length as f64;
+        if total == 0.0 {
+            return 0.0;
+        }
+
+        return used / total;
+    }
+}
+
+// A box with any number of sides, each in feet, as read from a manifest
+// line such as "2x3x4", "1.5ftx20inx30cm" or "2x3x4x5".  Sides without a
+// unit are in feet.  Presents::manifest_report() totals a whole manifest.
+#[derive(Debug, PartialEq)]
+pub struct BoxSpec {
+    pub sides: Vec<f64>,
+}
+
+#[allow(dead_code)]
+impl BoxSpec {
+    pub fn parse(spec: &str) -> Result<BoxSpec, String> {
+        lazy_static! {
+            static ref SIDE_RE: Regex = Regex::new("^([0-9]+(?:\\.[0-9]+)?)\\s*(in|ft|cm)?$").unwrap();
+        }
+
+        let mut sides = Vec::new();
+        for side in spec.trim().split('x') {
+            match SIDE_RE.captures(side.trim()) {
+                Some(cap) => {
+                    let value: f64 = cap[1].parse().unwrap();
+                    let feet = match cap.get(2).map(|m| m.as_str()) {
+                        Some("in") => value / 12.0,
+                        Some("cm") => value / 30.48,
+                        _ => value,
+                    };
+                    if !feet.is_finite() {
+                        return Err(format!("Side '{}' is too large.", side));
+                    }
+                    sides.push(feet);
+                }
+                None => return Err(format!("Bad side '{}' in box '{}'.", side, spec)),
+            }
+        }
+
+        if sides.len() < 2 {
+            return Err(format!("Box '{}' needs at least two sides.", spec));
+        }
+
+        return Ok(BoxSpec { sides: sides });
+    }
+
+    // Product of all sides except the one at index skip.
+    fn facet(&self, skip: usize) -> f64 {
+        return self.sides.iter().enumerate()
+            .filter(|(n, _)| *n != skip)
+            .map(|(_, side)| side)
+            .product();
+    }
+
+    // Square feet of paper: the area of every facet, two of each, plus the
+    // smallest facet as slack.  For three sides this is paper_required().
+    pub fn paper(&self) -> Result<f64, String> {
+        let facets: Vec<f64> = (0..self.sides.len()).map(|n| self.facet(n)).collect();
+        let smallest = facets.iter().cloned().fold(f64::INFINITY, f64::min);
+        let area = 2.0 * facets.iter().sum::<f64>() + smallest;
+        if !area.is_finite() {
+            return Err(format!("Paper for box {:?} overflows.", self.sides));
+        }
+
+        return Ok(area);
+    }
+
+    // Cubic feet, or its equivalent for more than three sides.
+    pub fn volume(&self) -> f64 {
+        return self.sides.iter().product();
+    }
+
+    // Feet of ribbon: the smallest perimeter of any face plus the volume for
+    // the bow.  For three sides this is ribbon_required().
+    pub fn ribbon(&self) -> Result<f64, String> {
+        let mut sorted = self.sides.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let length = 2.0*(sorted[0] + sorted[1]) + sorted.iter().product::<f64>();
+        if !length.is_finite() {
+            return Err(format!("Ribbon for box {:?} overflows.", self.sides));
+        }
+
+        return Ok(length);
+    }
 }
 
 impl Presents {
     pub fn load(filename: &str) -> Presents {
+        return Presents::try_load(filename).unwrap();
+    }
+
+    // Read whole-foot LxWxH boxes.  Sides too large for 64 bits are an error
+    // naming the line.
+    pub fn try_load(filename: &str) -> Result<Presents, String> {
+        let file = File::open(filename).map_err(|e| format!("{}: {}", filename, e))?;
+        let reader = BufReader::new(file);
+        let mut dims = Vec::new();
+
+        for (n, line) in reader.lines().enumerate() {
+            let l = line.map_err(|e| format!("{}: {}", filename, e))?;
+            if let Some(d) = Presents::parse_dims(&l).map_err(|e| format!("{} line {}: {}", filename, n+1, e))? {
+                dims.push(d);
+            }
+        }
+
+        return Ok(Presents { dims: dims });
+    }
+
+    // Dimensions from an LxWxH line, or None if the line holds none.
+    fn parse_dims(l: &str) -> Result<Option<Dims>, String> {
         lazy_static! {
             static ref LWH_RE: Regex = Regex::new("([0-9]+)x([0-9]+)x([0-9]+)").unwrap();
         }
-        let file = File::open(filename).unwrap();
+
+        match LWH_RE.captures(l) {
+            Some(cap) => {
+                let side = |n: usize| -> Result<u64, String> {
+                    cap[n].parse().map_err(|_| format!("Side {} overflows 64 bits.", &cap[n]))
+                };
+                return Ok(Some((side(1)?, side(2)?, side(3)?)));
+            }
+            None => return Ok(None),
+        }
+    }
+
+    // Read a manifest of general box specs, one per line; see BoxSpec.
+    #[allow(dead_code)]
+    pub fn load_manifest(filename: &str) -> Result<Vec<BoxSpec>, String> {
+        let file = File::open(filename).map_err(|e| format!("{}: {}", filename, e))?;
         let reader = BufReader::new(file);
-        let mut dims = Vec::new();
+        let mut specs = Vec::new();
 
-        for line in reader.lines() {
-            let l = &line.unwrap();
-            match LWH_RE.captures(l) {
-                Some(cap) => {
-                    // We have a match for a mask instruction
-                    dims.push((cap[1].parse().unwrap(),
-                               cap[2].parse().unwrap(),
-                               cap[3].parse().unwrap()));
+        for (n, line) in reader.lines().enumerate() {
+            let l = line.map_err(|e| format!("{}: {}", filename, e))?;
+            if l.trim().is_empty() {
+                continue;
+            }
+            let spec = BoxSpec::parse(&l).map_err(|e| format!("{} line {}: {}", filename, n+1, e))?;
+            specs.push(spec);
+        }
+
+        return Ok(specs);
+    }
+
+    fn overflow(what: &str, dims: &Dims) -> String {
+        return format!("{} for {}x{}x{} box overflows 64 bits.", what, dims.0, dims.1, dims.2);
+    }
+
+    fn volume(dims: &Dims) -> Result<u64, String> {
+        return dims.0.checked_mul(dims.1)
+            .and_then(|a| a.checked_mul(dims.2))
+            .ok_or(Presents::overflow("Volume", dims));
+    }
+
+    pub fn paper_required(dims: &Dims) -> Result<u64, String> {
+        let area = || -> Option<u64> {
+            let a1 = dims.0.checked_mul(dims.1)?;
+            let a2 = dims.1.checked_mul(dims.2)?;
+            let a3 = dims.2.checked_mul(dims.0)?;
+            let min_area = a1.min(a2).min(a3);
+
+            return a1.checked_add(a2)?.checked_add(a3)?.checked_mul(2)?.checked_add(min_area);
+        };
+
+        return area().ok_or(Presents::overflow("Paper", dims));
+    }
+
+    pub fn ribbon_required(dims: &Dims) -> Result<u64, String> {
+        let vol = Presents::volume(dims)?;
+        let length = || -> Option<u64> {
+            let mut sides = [dims.0, dims.1, dims.2];
+            sides.sort();
+            let min_perim = sides[0].checked_add(sides[1])?.checked_mul(2)?;
+
+            return min_perim.checked_add(vol);
+        };
+
+        return length().ok_or(Presents::overflow("Ribbon", dims));
+    }
+
+    // Rectangle of paper that wraps a box: a band around its two smallest
+    // dimensions, long enough to fold over both ends.
+    #[allow(dead_code)]
+    pub fn wrap_piece(dims: &Dims) -> Result<(u64, u64), String> {
+        let mut sides = [dims.0, dims.1, dims.2];
+        sides.sort();
+        let band = sides[0].checked_add(sides[1]).and_then(|p| p.checked_mul(2));
+        let length = sides[2].checked_add(sides[0]);
+
+        return band.zip(length).ok_or(Presents::overflow("Wrap piece", dims));
+    }
+
+    // Lay out every box's wrap piece on a roll of the given width, trying to
+    // use as little roll length as possible.  Pieces are placed first-fit on
+    // shelves across the roll, tallest shelves first.
+    #[allow(dead_code)]
+    pub fn cut_layout(&self, roll_width: u64) -> Result<CutLayout, String> {
+        // (index, across, along) for each piece, in the orientation that
+        // keeps the shelf shortest while still fitting the roll.
+        let mut pieces: Vec<(usize, u64, u64)> = Vec::new();
+        for (index, dim) in self.dims.iter().enumerate() {
+            let (a, b) = Presents::wrap_piece(dim)?;
+            let (long, short) = if a >= b { (a, b) } else { (b, a) };
+            if long <= roll_width {
+                pieces.push((index, long, short));
+            }
+            else if short <= roll_width {
+                pieces.push((index, short, long));
+            }
+            else {
+                return Err(format!("Box {} needs a {}x{} piece, wider than the {} ft roll.",
+                                   index, a, b, roll_width));
+            }
+        }
+        pieces.sort_by(|p, q| q.2.cmp(&p.2).then(p.0.cmp(&q.0)));
+
+        // Shelves are (y, height, width used so far).
+        let mut shelves: Vec<(u64, u64, u64)> = Vec::new();
+        let mut roll_length: u64 = 0;
+        let mut placements = Vec::new();
+        for (index, across, along) in pieces {
+            // An existing shelf may take the piece either way round.
+            let fits = |s: &(u64, u64, u64), w: u64, h: u64| roll_width - s.2 >= w && h <= s.1;
+            let mut placed = None;
+            for s in shelves.iter_mut() {
+                for (w, h) in [(across, along), (along, across)] {
+                    if placed.is_none() && fits(s, w, h) {
+                        placed = Some(Placement { index: index, x: s.2, y: s.0, across: w, along: h });
+                        s.2 += w;
+                    }
+                }
+            }
+
+            match placed {
+                Some(p) => placements.push(p),
+                None => {
+                    placements.push(Placement { index: index, x: 0, y: roll_length, across: across, along: along });
+                    shelves.push((roll_length, along, across));
+                    roll_length = roll_length.checked_add(along)
+                        .ok_or("Roll length overflows 64 bits.".to_string())?;
                 }
-                _ => {}
             }
         }
 
-        return Presents { dims: dims }
+        return Ok(CutLayout { roll_width: roll_width, roll_length: roll_length, placements: placements });
     }
 
-    pub fn paper_required(dims: &(u32, u32, u32)) -> u32 {
-        let a1 = dims.0*dims.1;
-        let a2 = dims.1*dims.2;
-        let a3 = dims.2*dims.0;
+    pub fn report(&self) -> Result<OrderReport, String> {
+        let mut boxes = Vec::new();
+        let mut total_paper: u64 = 0;
+        let mut total_ribbon: u64 = 0;
 
-        let mut min_area = a1;
-        if a2 < min_area { min_area = a2; }
-        if a3 < min_area { min_area = a3; }
+        for (index, dim) in self.dims.iter().enumerate() {
+            let volume = Presents::volume(dim)?;
+            let paper = Presents::paper_required(dim)?;
+            let ribbon = Presents::ribbon_required(dim)?;
+            total_paper = total_paper.checked_add(paper)
+                .ok_or("Total paper overflows 64 bits.".to_string())?;
+            total_ribbon = total_ribbon.checked_add(ribbon)
+                .ok_or("Total ribbon overflows 64 bits.".to_string())?;
+            boxes.push(BoxUsage { index: index, dims: *dim, volume: volume, paper: paper, ribbon: ribbon });
+        }
 
-        return 2*a1 + 2*a2 + 2*a3 + min_area;
+        return Ok(OrderReport { boxes: boxes, total_paper: total_paper, total_ribbon: total_ribbon });
     }
 
-    pub fn ribbon_required(dims: &(u32, u32, u32)) -> u32 {
-        let vol = dims.0*dims.1*dims.2;
-        let p1 = 2*(dims.0+dims.1);
-        let p2 = 2*(dims.1+dims.2);
-        let p3 = 2*(dims.2+dims.0);
+    // Like report(), for boxes read by load_manifest().
+    #[allow(dead_code)]
+    pub fn manifest_report(specs: &[BoxSpec]) -> Result<ManifestReport, String> {
+        let mut boxes = Vec::new();
+        let mut total_paper = 0.0;
+        let mut total_ribbon = 0.0;
 
-        let mut min_perim = p1;
-        if p2 < min_perim { min_perim = p2; }
-        if p3 < min_perim { min_perim = p3; }
+        for (index, spec) in specs.iter().enumerate() {
+            let paper = spec.paper()?;
+            let ribbon = spec.ribbon()?;
+            total_paper += paper;
+            total_ribbon += ribbon;
+            boxes.push(SpecUsage { index, sides: spec.sides.clone(), volume: spec.volume(), paper, ribbon });
+        }
+        if !total_paper.is_finite() {
+            return Err("Total paper overflows.".to_string());
+        }
+        if !total_ribbon.is_finite() {
+            return Err("Total ribbon overflows.".to_string());
+        }
 
-        return min_perim + vol;
+        return Ok(ManifestReport { boxes, total_paper, total_ribbon });
     }
 }
 
 impl super::Day for Presents {
     fn part1(&mut self) -> Result<i64, &str> {
-        let mut accum = 0;
-        for dim in &self.dims {
-            accum += Presents::paper_required(&dim);
+        match self.report() {
+            Ok(report) if report.total_paper <= i64::MAX as u64 => return Ok(report.total_paper as i64),
+            _ => return Err("Paper required overflows."),
         }
-
-        return Ok(accum as i64);
     }
 
     fn part2(&mut self) -> Result<i64, &str> {
-        let mut accum = 0;
-        for dim in &self.dims {
-            accum += Presents::ribbon_required(&dim);
+        match self.report() {
+            Ok(report) if report.total_ribbon <= i64::MAX as u64 => return Ok(report.total_ribbon as i64),
+            _ => return Err("Ribbon required overflows."),
         }
-
-        return Ok(accum as i64);
     }
 }
 
@@ -82,33 +474,205 @@ impl super::Day for Presents {
 mod tests {
     use super::*;
     use crate::Day;
+    use std::fs;
 
     #[test]
     fn test_paper_required() {
-        let examples: Vec<((u32, u32, u32), u32)> = vec![
+        let examples: Vec<(Dims, u64)> = vec![
             ((2, 3, 4), 58),
             ((1, 1, 10), 43),
         ];
 
         for (dims, expected) in examples {
             let area = Presents::paper_required(&dims);
-            assert_eq!(area, expected);
+            assert_eq!(area, Ok(expected));
         }
     }
 
     #[test]
     fn test_ribbon_required() {
-        let examples: Vec<((u32, u32, u32), u32)> = vec![
+        let examples: Vec<(Dims, u64)> = vec![
             ((2, 3, 4), 34),
             ((1, 1, 10), 14),
         ];
 
         for (dims, expected) in examples {
             let feet = Presents::ribbon_required(&dims);
-            assert_eq!(feet, expected);
+            assert_eq!(feet, Ok(expected));
         }
     }
 
+    #[test]
+    fn test_overflow() {
+        // Would have overflowed u32
+        assert_eq!(Presents::paper_required(&(100000, 100000, 1)), Ok(20000000000 + 400000 + 100000));
+
+        let huge = 1u64 << 32;
+        assert!(Presents::paper_required(&(huge, huge, 1)).is_err());
+        assert!(Presents::ribbon_required(&(huge, huge, huge)).is_err());
+
+        let mut day = Presents { dims: vec![(huge, huge, 1)] };
+        assert!(day.report().is_err());
+        assert!(day.part1().is_err());
+    }
+
+    #[test]
+    fn test_try_load() {
+        let path = std::env::temp_dir().join(format!("presents_{}.txt", std::process::id()));
+        let filename = path.to_str().unwrap();
+
+        fs::write(&path, "2x3x4\n1x1x10\n").unwrap();
+        assert_eq!(Presents::try_load(filename).unwrap().dims, vec![(2, 3, 4), (1, 1, 10)]);
+
+        fs::write(&path, "2x3x4\n99999999999999999999x1x1\n").unwrap();
+        assert_eq!(Presents::try_load(filename).err(),
+                   Some(format!("{} line 2: Side 99999999999999999999 overflows 64 bits.", filename)));
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(Presents::parse_dims("18446744073709551615x1x1"), Ok(Some((u64::MAX, 1, 1))));
+        assert_eq!(Presents::parse_dims(""), Ok(None));
+    }
+
+    #[test]
+    fn test_box_spec() {
+        assert_eq!(BoxSpec::parse("2x3x4"), Ok(BoxSpec { sides: vec![2.0, 3.0, 4.0] }));
+        assert_eq!(BoxSpec::parse("1.5ftx24inx30.48cm"), Ok(BoxSpec { sides: vec![1.5, 2.0, 1.0] }));
+        assert_eq!(BoxSpec::parse("1x2x3x4").unwrap().sides.len(), 4);
+        assert!(BoxSpec::parse("2x3xfoo").is_err());
+        assert!(BoxSpec::parse("2mx3x4").is_err());
+        assert!(BoxSpec::parse("7").is_err());
+
+        // Three sides agree with the integer calculations
+        let spec = BoxSpec::parse("2x3x4").unwrap();
+        assert_eq!(spec.paper(), Ok(58.0));
+        assert_eq!(spec.ribbon(), Ok(34.0));
+
+        // Four sides: facets 24, 12, 8, 6
+        let spec = BoxSpec::parse("1x2x3x4").unwrap();
+        assert_eq!(spec.paper(), Ok(2.0*50.0 + 6.0));
+        assert_eq!(spec.ribbon(), Ok(6.0 + 24.0));
+
+        let spec = BoxSpec { sides: vec![1e300, 1e300, 1.0] };
+        assert!(spec.paper().is_err());
+    }
+
+    #[test]
+    fn test_report() {
+        let day = Presents { dims: vec![(2, 3, 4), (1, 1, 10), (1, 1, 1)] };
+        let report = day.report().unwrap();
+
+        assert_eq!(report.boxes.len(), 3);
+        assert_eq!(report.boxes[1].paper, 43);
+        assert_eq!(report.boxes[1].ribbon, 14);
+        assert_eq!(report.total_paper, 58 + 43 + 7);
+        assert_eq!(report.total_ribbon, 34 + 14 + 5);
+
+        assert_eq!(report.most_paper().unwrap().index, 0);
+        assert_eq!(report.least_paper().unwrap().index, 2);
+        assert_eq!(report.most_ribbon().unwrap().index, 0);
+        assert_eq!(report.least_ribbon().unwrap().index, 2);
+    }
+
+    #[test]
+    fn test_manifest_report() {
+        let path = std::env::temp_dir().join(format!("manifest_{}.txt", std::process::id()));
+        fs::write(&path, "2x3x4\n\n1x2x3x4\n6inx12inx1\n").unwrap();
+        let specs = Presents::load_manifest(path.to_str().unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let report = Presents::manifest_report(&specs).unwrap();
+        assert_eq!(report.boxes.len(), 3);
+        assert_eq!(report.boxes[1], SpecUsage { index: 1, sides: vec![1.0, 2.0, 3.0, 4.0], volume: 24.0,
+                                                paper: 106.0, ribbon: 30.0 });
+        // 0.5 x 1 x 1: facets 1, 0.5 and 0.5
+        assert_eq!(report.boxes[2].paper, 4.5);
+        assert_eq!(report.boxes[2].ribbon, 3.5);
+        assert_eq!(report.total_paper, 58.0 + 106.0 + 4.5);
+        assert_eq!(report.total_ribbon, 34.0 + 30.0 + 3.5);
+
+        // 168.5 square feet of paper
+        assert_eq!(report.rolls_needed(2.0, 50.0), Ok(2));
+        assert_eq!(report.rolls_needed(3.5, 50.0), Ok(1));
+        assert!(report.rolls_needed(0.0, 50.0).is_err());
+
+        let mut out: Vec<u8> = Vec::new();
+        report.write_csv(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "index,sides,volume,paper,ribbon\n\
+                    0,2x3x4,24,58,34\n\
+                    1,1x2x3x4,24,106,30\n\
+                    2,0.5x1x1,0.5,4.5,3.5\n");
+
+        let huge = BoxSpec { sides: vec![1e154, 1e154, 1.0] };
+        assert!(Presents::manifest_report(&[huge]).is_err());
+        assert_eq!(Presents::manifest_report(&[]).unwrap().total_paper, 0.0);
+    }
+
+    #[test]
+    fn test_volume_histogram() {
+        let day = Presents { dims: vec![(2, 3, 4), (1, 1, 10), (1, 1, 1)] };
+        let report = day.report().unwrap();
+        let histogram = report.volume_histogram(10).unwrap();
+
+        let expected: Vec<(u64, usize)> = vec![(0, 1), (10, 1), (20, 1)];
+        assert_eq!(histogram.into_iter().collect::<Vec<_>>(), expected);
+        assert!(report.volume_histogram(0).is_err());
+    }
+
+    #[test]
+    fn test_rolls_needed() {
+        let day = Presents { dims: vec![(2, 3, 4), (1, 1, 10)] };
+        let report = day.report().unwrap();
+
+        // 101 square feet of paper
+        assert_eq!(report.rolls_needed(2, 50), Ok(2));
+        assert_eq!(report.rolls_needed(3, 50), Ok(1));
+        assert!(report.rolls_needed(u64::MAX, 2).is_err());
+        assert_eq!(report.rolls_needed(0, 50), Err("A 0x50 roll has no paper.".to_string()));
+        assert!(report.rolls_needed(3, 0).is_err());
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let day = Presents { dims: vec![(2, 3, 4), (1, 1, 10)] };
+        let mut out: Vec<u8> = Vec::new();
+        day.report().unwrap().write_csv(&mut out).unwrap();
+
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "index,length,width,height,volume,paper,ribbon\n\
+                    0,2,3,4,24,58,34\n\
+                    1,1,1,10,10,43,14\n");
+    }
+
+    #[test]
+    fn test_wrap_piece() {
+        assert_eq!(Presents::wrap_piece(&(2, 3, 4)), Ok((10, 6)));
+        assert_eq!(Presents::wrap_piece(&(10, 1, 1)), Ok((4, 11)));
+    }
+
+    #[test]
+    fn test_cut_layout() {
+        // Pieces are 10x6, 4x11 and 4x2
+        let day = Presents { dims: vec![(2, 3, 4), (1, 1, 10), (1, 1, 1)] };
+
+        let layout = day.cut_layout(12).unwrap();
+        assert_eq!(layout.roll_length, 6 + 4);
+        assert_eq!(layout.placements, vec![
+            Placement { index: 0, x: 0, y: 0, across: 10, along: 6 },
+            Placement { index: 1, x: 0, y: 6, across: 11, along: 4 },
+            Placement { index: 2, x: 10, y: 0, across: 2, along: 4 },
+        ]);
+        assert!((layout.utilization() - 112.0 / 120.0).abs() < 1e-9);
+
+        // Too narrow for the 11 ft side, so that piece runs along the roll
+        // and the 10x6 piece turns to fit beside it.
+        let layout = day.cut_layout(10).unwrap();
+        assert_eq!(layout.roll_length, 11 + 2);
+        assert_eq!(layout.placements[1], Placement { index: 0, x: 4, y: 0, across: 6, along: 10 });
+
+        assert!(day.cut_layout(3).is_err());
+    }
+
     #[test]
     fn test_load() {
         let day = &Presents::load("data/day2_input.txt");
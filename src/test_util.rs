@@ -0,0 +1,19 @@
+// Helpers shared by the puzzle modules' tests.
+
+// A linear congruential generator, so randomized tests are repeatable
+// without pulling in a crate for random numbers.
+pub struct Lcg {
+    state: u64,
+}
+
+impl Lcg {
+    pub fn new(seed: u64) -> Lcg {
+        Lcg { state: seed }
+    }
+
+    // A number in [0, below).
+    pub fn below(&mut self, below: u64) -> u64 {
+        self.state = self.state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.state >> 33) % below
+    }
+}